@@ -0,0 +1,164 @@
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+use super::WindowManagerConfig;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid { key: String, reason: String },
+}
+
+impl ConfigError {
+    pub fn invalid(key: impl Into<String>, reason: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            key: key.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// The config key which caused the error, if it is known.
+    #[allow(dead_code)]
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            ConfigError::Invalid { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "failed to read {}: {}", path.display(), e),
+            // toml reports the line, column and key of the offending value by itself
+            ConfigError::Parse(path, e) => write!(f, "failed to parse {}: {}", path.display(), e),
+            ConfigError::Invalid { key, reason } => {
+                write!(f, "invalid value for `{}`: {}", key, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Returns `$XDG_CONFIG_HOME/clearwm/config.toml`, falling back to `$HOME/.config` as the base directory.
+pub fn config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("clearwm").join("config.toml"))
+}
+
+/// Loads the config file. The built-in defaults are used if the file does not exist.
+pub fn load_config() -> Result<WindowManagerConfig, ConfigError> {
+    let path = if let Some(path) = config_path() {
+        path
+    } else {
+        info!("No config directory found, using the default config");
+        return Ok(WindowManagerConfig::default());
+    };
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            info!("{} not found, using the default config", path.display());
+            return Ok(WindowManagerConfig::default());
+        }
+        Err(e) => return Err(ConfigError::Io(path, e)),
+    };
+
+    let config = parse_config(&text).map_err(|e| match e {
+        ConfigError::Parse(_, e) => ConfigError::Parse(path.clone(), e),
+        e => e,
+    })?;
+    info!("Loaded config from {}", path.display());
+    Ok(config)
+}
+
+fn parse_config(text: &str) -> Result<WindowManagerConfig, ConfigError> {
+    let config: WindowManagerConfig =
+        toml::from_str(text).map_err(|e| ConfigError::Parse(PathBuf::new(), e))?;
+    config.validate()?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Color, FrameConfig, TitlebarButton, TitlebarConfig};
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(
+            r##"
+            [frame]
+            border_width = 4
+            corner_radius = 8
+
+            [theme.active]
+            frame_color = "#000000"
+
+            [titlebar]
+            left = ["close"]
+            right = ["min", "maximize"]
+
+            [bindings.keys]
+            "Mod4+Shift+r" = "reload"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.frame_config,
+            FrameConfig {
+                border_width: 4,
+                titlebar_height: FrameConfig::default().titlebar_height,
+                corner_radius: 8,
+                ..FrameConfig::default()
+            }
+        );
+        assert_eq!(config.theme.active.frame_color, Color::BLACK);
+        assert_eq!(
+            config.titlebar_config,
+            TitlebarConfig {
+                left: vec![TitlebarButton::Close],
+                right: vec![TitlebarButton::Minimize, TitlebarButton::Maximize],
+            }
+        );
+        assert_eq!(
+            config.binding_config.keys.get("Mod4+Shift+r"),
+            Some(&String::from("reload"))
+        );
+
+        assert_eq!(
+            parse_config("").unwrap().frame_config,
+            FrameConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_config_error() {
+        // wrong type
+        let e = parse_config("[frame]\nborder_width = \"wide\"").unwrap_err();
+        assert!(e.to_string().contains("border_width"));
+
+        // unknown key
+        let e = parse_config("[frame]\nborder = 4").unwrap_err();
+        assert!(e.to_string().contains("border"));
+
+        // invalid color
+        let e = parse_config("[theme.active]\ntitle_color = \"white\"").unwrap_err();
+        assert!(e.to_string().contains("title_color"));
+
+        // failed validation
+        let e = parse_config("[frame]\ntitlebar_height = 0").unwrap_err();
+        assert_eq!(e.key(), Some("frame.titlebar_height"));
+    }
+}
@@ -1,11 +1,385 @@
-#[derive(Debug, Clone)]
+mod file;
+mod theme;
+
+use std::collections::BTreeMap;
+
+use serde::{de, Deserialize, Deserializer};
+
+pub use file::{load_config, ConfigError};
+pub use theme::Theme;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct WindowManagerConfig {
+    #[serde(rename = "frame")]
     pub frame_config: FrameConfig,
+    pub theme: Theme,
+    #[serde(rename = "titlebar")]
+    pub titlebar_config: TitlebarConfig,
+    #[serde(rename = "close")]
+    pub close_config: CloseConfig,
+    #[serde(rename = "ping")]
+    pub ping_config: PingConfig,
+    #[serde(rename = "bindings")]
+    pub binding_config: BindingConfig,
+    #[serde(rename = "workspace")]
+    pub workspace_config: WorkspaceConfig,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct FrameConfig {
     pub border_width: u32,
     pub titlebar_height: u32,
     pub corner_radius: u32,
+    /// Minimum size of the visible frame including the titlebar,
+    /// which keeps the titlebar usable regardless of the size hints of the clients.
+    pub min_width: u32,
+    pub min_height: u32,
+}
+
+impl Default for FrameConfig {
+    fn default() -> Self {
+        Self {
+            border_width: 18,
+            titlebar_height: 20,
+            corner_radius: 24,
+            min_width: 120,
+            min_height: 40,
+        }
+    }
+}
+
+impl FrameConfig {
+    /// The configuration of the clients shown without the titlebar and the border.
+    pub fn frameless(self) -> Self {
+        Self {
+            border_width: 0,
+            titlebar_height: 0,
+            corner_radius: 0,
+            ..self
+        }
+    }
+}
+
+/// Buttons placed at the left and right ends of the titlebar, in the order they are drawn.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TitlebarConfig {
+    pub left: Vec<TitlebarButton>,
+    pub right: Vec<TitlebarButton>,
+}
+
+impl Default for TitlebarConfig {
+    fn default() -> Self {
+        Self {
+            left: Vec::new(),
+            right: vec![
+                TitlebarButton::Minimize,
+                TitlebarButton::Maximize,
+                TitlebarButton::Close,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TitlebarButton {
+    #[serde(rename = "close")]
+    Close,
+    #[serde(rename = "max", alias = "maximize")]
+    Maximize,
+    #[serde(rename = "min", alias = "minimize")]
+    Minimize,
+    /// Kills the client. Shown in place of the close button while the client is not responding.
+    #[serde(skip)]
+    ForceKill,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CloseConfig {
+    /// Seconds to wait for a client to close itself before killing its process.
+    /// The client answering _NET_WM_PING meanwhile is not killed, as it is still alive.
+    /// Clients are never killed by the timeout if it is not set.
+    pub force_kill_after: Option<u64>,
+}
+
+/// Liveness checks of the clients supporting _NET_WM_PING.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PingConfig {
+    /// Seconds between the pings to every client.
+    /// Clients are pinged only when they are asked to close if it is not set.
+    pub interval: Option<u64>,
+    /// Seconds to wait for the reply before the client is shown as not responding.
+    pub timeout: u64,
+}
+
+impl Default for PingConfig {
+    fn default() -> Self {
+        Self {
+            interval: Some(10),
+            timeout: 5,
+        }
+    }
+}
+
+/// Key bindings, mapping a key combination such as `Mod4+Shift+r` to an action name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BindingConfig {
+    pub keys: BTreeMap<String, String>,
+}
+
+/// Virtual desktops, created in the order of the names.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    pub names: Vec<String>,
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        Self {
+            names: ["1", "2", "3", "4"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// RGBA color with components in `0.0..=1.0`, written as `#rrggbb` or `#rrggbbaa` in the config file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const TRANSPARENT: Color = Color::BLACK.with_alpha(0.0);
+
+    pub const fn rgb(r: f64, g: f64, b: f64) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    pub const fn with_alpha(self, a: f64) -> Self {
+        Self { a, ..self }
+    }
+
+    pub fn parse_hex(hex: &str) -> Option<Self> {
+        let digits = hex.strip_prefix('#')?;
+        if !digits.is_ascii() || (digits.len() != 6 && digits.len() != 8) {
+            return None;
+        }
+        let component = |i: usize| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .ok()
+                .map(|c| c as f64 / 255.0)
+        };
+        Some(Self {
+            r: component(0)?,
+            g: component(2)?,
+            b: component(4)?,
+            a: if digits.len() == 8 {
+                component(6)?
+            } else {
+                1.0
+            },
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        Color::parse_hex(&hex).ok_or_else(|| {
+            de::Error::invalid_value(de::Unexpected::Str(&hex), &"a color like \"#rrggbb\"")
+        })
+    }
+}
+
+const KEY_MODIFIERS: [&str; 9] = [
+    "Shift", "Control", "Ctrl", "Alt", "Mod1", "Mod2", "Mod3", "Mod4", "Mod5",
+];
+
+// Large values would overflow the 16-bit window sizes of the X protocol.
+const MAX_FRAME_SIZE: u32 = 256;
+
+impl WindowManagerConfig {
+    /// Checks the values which cannot be expressed by types.
+    /// The error names the offending key in the config file.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let frame = &self.frame_config;
+        for (key, value) in [
+            ("frame.border_width", frame.border_width),
+            ("frame.titlebar_height", frame.titlebar_height),
+            ("frame.corner_radius", frame.corner_radius),
+        ] {
+            if value > MAX_FRAME_SIZE {
+                return Err(ConfigError::invalid(
+                    key,
+                    format!("must be at most {}, got {}", MAX_FRAME_SIZE, value),
+                ));
+            }
+        }
+        if frame.titlebar_height == 0 {
+            return Err(ConfigError::invalid(
+                "frame.titlebar_height",
+                "must be greater than 0",
+            ));
+        }
+        if frame.corner_radius < frame.border_width {
+            return Err(ConfigError::invalid(
+                "frame.corner_radius",
+                format!(
+                    "must not be smaller than frame.border_width ({})",
+                    frame.border_width
+                ),
+            ));
+        }
+
+        self.theme.validate()?;
+
+        let titlebar = &self.titlebar_config;
+        for (side, buttons) in [("left", &titlebar.left), ("right", &titlebar.right)] {
+            for (i, button) in buttons.iter().enumerate() {
+                if titlebar
+                    .left
+                    .iter()
+                    .chain(&titlebar.right)
+                    .filter(|b| *b == button)
+                    .count()
+                    > 1
+                {
+                    return Err(ConfigError::invalid(
+                        format!("titlebar.{}[{}]", side, i),
+                        format!("{:?} button appears more than once", button),
+                    ));
+                }
+            }
+        }
+
+        if self.close_config.force_kill_after == Some(0) {
+            return Err(ConfigError::invalid(
+                "close.force_kill_after",
+                "must be greater than 0",
+            ));
+        }
+
+        if self.ping_config.interval == Some(0) {
+            return Err(ConfigError::invalid(
+                "ping.interval",
+                "must be greater than 0",
+            ));
+        }
+        if self.ping_config.timeout == 0 {
+            return Err(ConfigError::invalid(
+                "ping.timeout",
+                "must be greater than 0",
+            ));
+        }
+
+        for (combination, action) in &self.binding_config.keys {
+            let key = format!("bindings.keys.\"{}\"", combination);
+            let mut parts = combination.split('+').collect::<Vec<_>>();
+            let keysym = parts.pop().unwrap_or_default();
+            if keysym.is_empty() {
+                return Err(ConfigError::invalid(
+                    key,
+                    "missing a key after the modifiers",
+                ));
+            }
+            if let Some(modifier) = parts.iter().find(|m| !KEY_MODIFIERS.contains(m)) {
+                return Err(ConfigError::invalid(
+                    key,
+                    format!("unknown modifier \"{}\"", modifier),
+                ));
+            }
+            if action.trim().is_empty() {
+                return Err(ConfigError::invalid(key, "action must not be empty"));
+            }
+        }
+
+        if self.workspace_config.names.is_empty() {
+            return Err(ConfigError::invalid(
+                "workspace.names",
+                "must have at least one name",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(Color::parse_hex("#ff0000"), Some(Color::rgb(1.0, 0.0, 0.0)));
+        assert_eq!(Color::parse_hex("#00000000"), Some(Color::TRANSPARENT));
+        assert_eq!(Color::parse_hex("ff0000"), None);
+        assert_eq!(Color::parse_hex("#ff00"), None);
+        assert_eq!(Color::parse_hex("#gg0000"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(WindowManagerConfig::default().validate().is_ok());
+
+        let mut config = WindowManagerConfig::default();
+        config.frame_config.titlebar_height = 0;
+        assert_eq!(
+            config.validate().unwrap_err().key(),
+            Some("frame.titlebar_height")
+        );
+
+        let mut config = WindowManagerConfig::default();
+        config.frame_config.corner_radius = 4;
+        assert_eq!(
+            config.validate().unwrap_err().key(),
+            Some("frame.corner_radius")
+        );
+
+        let mut config = WindowManagerConfig::default();
+        config.close_config.force_kill_after = Some(0);
+        assert_eq!(
+            config.validate().unwrap_err().key(),
+            Some("close.force_kill_after")
+        );
+
+        let mut config = WindowManagerConfig::default();
+        config.ping_config.timeout = 0;
+        assert_eq!(config.validate().unwrap_err().key(), Some("ping.timeout"));
+
+        let mut config = WindowManagerConfig::default();
+        config.titlebar_config.left = vec![TitlebarButton::Close];
+        assert_eq!(
+            config.validate().unwrap_err().key(),
+            Some("titlebar.left[0]")
+        );
+
+        let mut config = WindowManagerConfig::default();
+        config
+            .binding_config
+            .keys
+            .insert(String::from("Hyper+q"), String::from("close"));
+        assert_eq!(
+            config.validate().unwrap_err().key(),
+            Some("bindings.keys.\"Hyper+q\"")
+        );
+
+        let mut config = WindowManagerConfig::default();
+        config.workspace_config.names.clear();
+        assert_eq!(
+            config.validate().unwrap_err().key(),
+            Some("workspace.names")
+        );
+    }
 }
@@ -0,0 +1,347 @@
+use serde::{Deserialize, Deserializer};
+
+use super::{Color, ConfigError};
+
+/// Appearance of the frames, with a style for each state of a client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub title_font: String,
+    /// Font size relative to the titlebar height.
+    pub title_font_ratio: f64,
+    /// Margin around the title relative to the titlebar height.
+    pub title_margin_ratio: f64,
+    pub active: FrameStyle,
+    pub inactive: FrameStyle,
+    pub urgent: FrameStyle,
+    /// Style of the clients which have not answered _NET_WM_PING.
+    pub not_responding: FrameStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStyle {
+    pub frame_color: Color,
+    pub title_color: Color,
+    pub title_bold: bool,
+    /// Color of the line drawn around the frame.
+    pub border_color: Color,
+    pub border_line_width: f64,
+    pub shadow_color: Color,
+    /// Extent of the drop shadow in pixels, limited by the border width of the frame.
+    pub shadow_size: f64,
+    pub shadow_offset: (f64, f64),
+    /// Background of the titlebar buttons. The symbols are drawn with the title color.
+    pub button_color: Color,
+    pub button_hover_color: Color,
+    pub button_pressed_color: Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreset {
+    #[default]
+    Default,
+    Dark,
+    HighContrast,
+}
+
+impl Theme {
+    pub fn preset(preset: ThemePreset) -> Self {
+        match preset {
+            ThemePreset::Default => {
+                let active = FrameStyle {
+                    frame_color: Color::rgb(0.5, 0.4, 0.3),
+                    title_color: Color::rgb(0.95, 0.95, 0.95),
+                    title_bold: true,
+                    border_color: Color::TRANSPARENT,
+                    border_line_width: 0.0,
+                    shadow_color: Color::BLACK.with_alpha(0.3),
+                    shadow_size: 6.0,
+                    shadow_offset: (0.0, 0.0),
+                    button_color: Color::TRANSPARENT,
+                    button_hover_color: Color::WHITE.with_alpha(0.25),
+                    button_pressed_color: Color::BLACK.with_alpha(0.25),
+                };
+                Self {
+                    title_font: String::from("Arial"),
+                    title_font_ratio: 0.6,
+                    title_margin_ratio: 0.2,
+                    active,
+                    inactive: FrameStyle {
+                        frame_color: Color::rgb(0.62, 0.56, 0.5),
+                        title_color: Color::rgb(0.85, 0.85, 0.85),
+                        title_bold: false,
+                        shadow_color: Color::BLACK.with_alpha(0.15),
+                        shadow_size: 4.0,
+                        ..active
+                    },
+                    urgent: FrameStyle {
+                        frame_color: Color::rgb(0.75, 0.35, 0.25),
+                        ..active
+                    },
+                    not_responding: FrameStyle {
+                        frame_color: Color::rgb(0.45, 0.43, 0.41),
+                        title_color: Color::rgb(0.75, 0.75, 0.75),
+                        title_bold: false,
+                        ..active
+                    },
+                }
+            }
+            ThemePreset::Dark => {
+                let active = FrameStyle {
+                    frame_color: Color::rgb(0.16, 0.17, 0.2),
+                    title_color: Color::rgb(0.9, 0.9, 0.92),
+                    title_bold: true,
+                    border_color: Color::rgb(0.3, 0.32, 0.38),
+                    border_line_width: 1.0,
+                    shadow_color: Color::BLACK.with_alpha(0.5),
+                    shadow_size: 8.0,
+                    shadow_offset: (0.0, 2.0),
+                    button_color: Color::TRANSPARENT,
+                    button_hover_color: Color::WHITE.with_alpha(0.15),
+                    button_pressed_color: Color::BLACK.with_alpha(0.3),
+                };
+                Self {
+                    title_font: String::from("Sans"),
+                    title_font_ratio: 0.6,
+                    title_margin_ratio: 0.2,
+                    active,
+                    inactive: FrameStyle {
+                        frame_color: Color::rgb(0.22, 0.23, 0.26),
+                        title_color: Color::rgb(0.55, 0.56, 0.6),
+                        title_bold: false,
+                        border_color: Color::rgb(0.26, 0.27, 0.31),
+                        shadow_color: Color::BLACK.with_alpha(0.25),
+                        shadow_size: 4.0,
+                        ..active
+                    },
+                    urgent: FrameStyle {
+                        frame_color: Color::rgb(0.55, 0.2, 0.2),
+                        ..active
+                    },
+                    not_responding: FrameStyle {
+                        frame_color: Color::rgb(0.12, 0.12, 0.13),
+                        title_color: Color::rgb(0.45, 0.45, 0.48),
+                        title_bold: false,
+                        ..active
+                    },
+                }
+            }
+            ThemePreset::HighContrast => {
+                let active = FrameStyle {
+                    frame_color: Color::BLACK,
+                    title_color: Color::WHITE,
+                    title_bold: true,
+                    border_color: Color::WHITE,
+                    border_line_width: 2.0,
+                    shadow_color: Color::TRANSPARENT,
+                    shadow_size: 0.0,
+                    shadow_offset: (0.0, 0.0),
+                    button_color: Color::TRANSPARENT,
+                    button_hover_color: Color::WHITE.with_alpha(0.4),
+                    button_pressed_color: Color::rgb(1.0, 1.0, 0.0).with_alpha(0.6),
+                };
+                Self {
+                    title_font: String::from("Sans"),
+                    title_font_ratio: 0.7,
+                    title_margin_ratio: 0.15,
+                    active,
+                    inactive: FrameStyle {
+                        title_color: Color::rgb(0.7, 0.7, 0.7),
+                        title_bold: false,
+                        border_color: Color::rgb(0.5, 0.5, 0.5),
+                        border_line_width: 1.0,
+                        ..active
+                    },
+                    urgent: FrameStyle {
+                        frame_color: Color::rgb(1.0, 1.0, 0.0),
+                        title_color: Color::BLACK,
+                        ..active
+                    },
+                    not_responding: FrameStyle {
+                        frame_color: Color::rgb(0.3, 0.3, 0.3),
+                        title_color: Color::WHITE,
+                        ..active
+                    },
+                }
+            }
+        }
+    }
+
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        if self.title_font.trim().is_empty() {
+            return Err(ConfigError::invalid(
+                "theme.title_font",
+                "must not be empty",
+            ));
+        }
+        for (key, ratio) in [
+            ("theme.title_font_ratio", self.title_font_ratio),
+            ("theme.title_margin_ratio", self.title_margin_ratio),
+        ] {
+            if !(ratio > 0.0 && ratio <= 1.0) {
+                return Err(ConfigError::invalid(
+                    key,
+                    format!("must be in the range (0, 1], got {}", ratio),
+                ));
+            }
+        }
+        for (name, style) in [
+            ("active", &self.active),
+            ("inactive", &self.inactive),
+            ("urgent", &self.urgent),
+            ("not_responding", &self.not_responding),
+        ] {
+            for (field, value) in [
+                ("border_line_width", style.border_line_width),
+                ("shadow_size", style.shadow_size),
+            ] {
+                if value.is_nan() || value < 0.0 {
+                    return Err(ConfigError::invalid(
+                        format!("theme.{}.{}", name, field),
+                        format!("must not be negative, got {}", value),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::preset(ThemePreset::Default)
+    }
+}
+
+/// The `[theme]` table as written in the config file.
+/// Every key is optional and overrides the value of the preset.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    preset: ThemePreset,
+    title_font: Option<String>,
+    title_font_ratio: Option<f64>,
+    title_margin_ratio: Option<f64>,
+    active: FrameStyleFile,
+    inactive: FrameStyleFile,
+    urgent: FrameStyleFile,
+    not_responding: FrameStyleFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FrameStyleFile {
+    frame_color: Option<Color>,
+    title_color: Option<Color>,
+    title_bold: Option<bool>,
+    border_color: Option<Color>,
+    border_line_width: Option<f64>,
+    shadow_color: Option<Color>,
+    shadow_size: Option<f64>,
+    shadow_offset: Option<(f64, f64)>,
+    button_color: Option<Color>,
+    button_hover_color: Option<Color>,
+    button_pressed_color: Option<Color>,
+}
+
+impl FrameStyleFile {
+    fn apply(self, style: &mut FrameStyle) {
+        let FrameStyleFile {
+            frame_color,
+            title_color,
+            title_bold,
+            border_color,
+            border_line_width,
+            shadow_color,
+            shadow_size,
+            shadow_offset,
+            button_color,
+            button_hover_color,
+            button_pressed_color,
+        } = self;
+        style.frame_color = frame_color.unwrap_or(style.frame_color);
+        style.title_color = title_color.unwrap_or(style.title_color);
+        style.title_bold = title_bold.unwrap_or(style.title_bold);
+        style.border_color = border_color.unwrap_or(style.border_color);
+        style.border_line_width = border_line_width.unwrap_or(style.border_line_width);
+        style.shadow_color = shadow_color.unwrap_or(style.shadow_color);
+        style.shadow_size = shadow_size.unwrap_or(style.shadow_size);
+        style.shadow_offset = shadow_offset.unwrap_or(style.shadow_offset);
+        style.button_color = button_color.unwrap_or(style.button_color);
+        style.button_hover_color = button_hover_color.unwrap_or(style.button_hover_color);
+        style.button_pressed_color = button_pressed_color.unwrap_or(style.button_pressed_color);
+    }
+}
+
+impl<'de> Deserialize<'de> for Theme {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let file = ThemeFile::deserialize(deserializer)?;
+        let mut theme = Theme::preset(file.preset);
+        theme.title_font = file.title_font.unwrap_or(theme.title_font);
+        theme.title_font_ratio = file.title_font_ratio.unwrap_or(theme.title_font_ratio);
+        theme.title_margin_ratio = file.title_margin_ratio.unwrap_or(theme.title_margin_ratio);
+        file.active.apply(&mut theme.active);
+        file.inactive.apply(&mut theme.inactive);
+        file.urgent.apply(&mut theme.urgent);
+        file.not_responding.apply(&mut theme.not_responding);
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_override() {
+        let theme: Theme = toml::from_str(
+            r##"
+            preset = "dark"
+            title_font = "Noto Sans"
+
+            [inactive]
+            frame_color = "#000000"
+            shadow_offset = [1.0, 3.0]
+            "##,
+        )
+        .unwrap();
+
+        let dark = Theme::preset(ThemePreset::Dark);
+        assert_eq!(theme.title_font, "Noto Sans");
+        assert_eq!(theme.title_font_ratio, dark.title_font_ratio);
+        assert_eq!(theme.active, dark.active);
+        assert_eq!(theme.inactive.frame_color, Color::BLACK);
+        assert_eq!(theme.inactive.shadow_offset, (1.0, 3.0));
+        assert_eq!(theme.inactive.title_color, dark.inactive.title_color);
+    }
+
+    #[test]
+    fn test_theme_validate() {
+        for preset in [
+            ThemePreset::Default,
+            ThemePreset::Dark,
+            ThemePreset::HighContrast,
+        ] {
+            assert!(Theme::preset(preset).validate().is_ok());
+        }
+
+        let theme = Theme {
+            title_font_ratio: 0.0,
+            ..Theme::default()
+        };
+        assert_eq!(
+            theme.validate().unwrap_err().key(),
+            Some("theme.title_font_ratio")
+        );
+
+        let mut theme = Theme::default();
+        theme.urgent.shadow_size = -1.0;
+        assert_eq!(
+            theme.validate().unwrap_err().key(),
+            Some("theme.urgent.shadow_size")
+        );
+    }
+}
@@ -1,9 +1,14 @@
 use log::{error, info};
-use wm::x11::window_manager::X11WindowManager;
+use std::os::unix::process::CommandExt;
+
+use wm::x11::{
+    command,
+    window_manager::{ExitRequest, X11WindowManager},
+};
 
 use crate::{
-    config::{FrameConfig, WindowManagerConfig},
-    wm::x11::session::X11Session,
+    config::WindowManagerConfig,
+    wm::x11::{command::Command, session::X11Session},
 };
 
 mod config;
@@ -12,19 +17,60 @@ mod model;
 mod wm;
 
 fn main() {
+    // `clearwm --replace` replaces the running window manager
+    let replace = std::env::args().nth(1).as_deref() == Some("--replace");
+
+    // `clearwm --<command>` controls the running window manager
+    if let Some(arg) = std::env::args().nth(1).filter(|_| !replace) {
+        let command = arg
+            .strip_prefix("--")
+            .and_then(Command::from_name)
+            .unwrap_or_else(|| {
+                eprintln!("Unknown argument: {}", arg);
+                std::process::exit(2);
+            });
+        command::send_command(command)
+            .unwrap_or_else(|e| panic!("Failed to send {:?}: {}", command, e));
+        return;
+    }
+
     logger::setup_logging(Some("wm.log")).expect("Failed to initialize logging");
     info!("Starting X11 window manager");
-    let wmconfig = WindowManagerConfig {
-        frame_config: FrameConfig {
-            border_width: 18,
-            titlebar_height: 20,
-            corner_radius: 24,
-        },
-    };
+    let wmconfig = config::load_config().unwrap_or_else(|e| {
+        error!("Failed to load config, using the default config: {}", e);
+        WindowManagerConfig::default()
+    });
     let session = X11Session::connect(wmconfig)
         .unwrap_or_else(|e| panic!("Failed to connect to X11 server: {}", e));
 
-    X11WindowManager::new(&session)
-        .start()
-        .unwrap_or_else(|e| error!("Error: {}", e));
+    let exit_request = match X11WindowManager::new(&session).start(replace) {
+        Ok(exit_request) => exit_request,
+        Err(e) => {
+            error!("Error: {}", e);
+            eprintln!("clearwm: {}", e);
+            log::logger().flush();
+            std::process::exit(1);
+        }
+    };
+    info!("Stopped X11 window manager");
+    log::logger().flush();
+
+    if exit_request == ExitRequest::Restart {
+        // close the connection before the new process connects
+        drop(session);
+        restart();
+    }
+}
+
+/// Replaces the process with the current binary, keeping the arguments except --replace.
+fn restart() {
+    let error = match std::env::current_exe() {
+        Ok(exe) => std::process::Command::new(exe)
+            .args(std::env::args().skip(1).filter(|arg| arg != "--replace"))
+            .exec(),
+        Err(e) => e,
+    };
+    error!("Failed to restart: {}", error);
+    log::logger().flush();
+    std::process::exit(1);
 }
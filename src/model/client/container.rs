@@ -5,6 +5,8 @@ where
     WinId: Copy + Eq,
 {
     clients: Vec<Client<WinId>>,
+    // from the bottom to the top
+    stacking: Vec<Client<WinId>>,
 }
 
 impl<WinId> ClientContainer<WinId>
@@ -14,12 +16,14 @@ where
     pub fn new() -> Self {
         Self {
             clients: Vec::new(),
+            stacking: Vec::new(),
         }
     }
 
     pub fn add_client(&mut self, app_id: WinId, frame_id: WinId) -> Client<WinId> {
         let client = Client { app_id, frame_id };
         self.clients.push(client);
+        self.stacking.push(client);
         client
     }
 
@@ -39,6 +43,30 @@ where
 
     pub fn remove_client(&mut self, client: Client<WinId>) {
         self.clients.retain(|c| c != &client);
+        self.stacking.retain(|c| c != &client);
+    }
+
+    /// Moves the client to the top of the stacking order.
+    pub fn raise_client(&mut self, client: Client<WinId>) {
+        if let Some(index) = self.stacking.iter().position(|c| c == &client) {
+            let client = self.stacking.remove(index);
+            self.stacking.push(client);
+        }
+    }
+
+    /// Sorts the stacking order by the key, keeping the order of the clients with the same key.
+    pub fn sort_stacking_by_key<K: Ord>(&mut self, key: impl FnMut(&Client<WinId>) -> K) {
+        self.stacking.sort_by_key(key);
+    }
+
+    /// Iterates the clients in the order they are added.
+    pub fn iter(&self) -> impl Iterator<Item = &Client<WinId>> {
+        self.clients.iter()
+    }
+
+    /// Iterates the clients from the bottom of the stacking order.
+    pub fn iter_stacking(&self) -> impl DoubleEndedIterator<Item = &Client<WinId>> {
+        self.stacking.iter()
     }
 }
 
@@ -66,4 +94,56 @@ mod tests {
         assert_eq!(container.query_client_from_app(5), None);
         assert_eq!(container.query_client_from_frame(6), None);
     }
+
+    #[test]
+    fn test_stacking_order() {
+        let mut container = ClientContainer::new();
+        let first = container.add_client(1, 2);
+        let second = container.add_client(3, 4);
+        let third = container.add_client(5, 6);
+        assert_eq!(
+            container.iter_stacking().copied().collect::<Vec<_>>(),
+            vec![first, second, third]
+        );
+
+        container.raise_client(first);
+        assert_eq!(
+            container.iter_stacking().copied().collect::<Vec<_>>(),
+            vec![second, third, first]
+        );
+        // the order of addition is kept
+        assert_eq!(
+            container.iter().copied().collect::<Vec<_>>(),
+            vec![first, second, third]
+        );
+
+        container.remove_client(third);
+        assert_eq!(
+            container.iter_stacking().copied().collect::<Vec<_>>(),
+            vec![second, first]
+        );
+    }
+
+    #[test]
+    fn test_sort_stacking_by_key() {
+        let mut container = ClientContainer::new();
+        let first = container.add_client(1, 2);
+        let second = container.add_client(3, 4);
+        let third = container.add_client(5, 6);
+        container.raise_client(first);
+
+        // a client with a higher key stays above the raised one
+        container.sort_stacking_by_key(|client| client.app_id == 3);
+        assert_eq!(
+            container.iter_stacking().copied().collect::<Vec<_>>(),
+            vec![third, first, second]
+        );
+
+        // the order within the same key is kept
+        container.sort_stacking_by_key(|_| 0);
+        assert_eq!(
+            container.iter_stacking().copied().collect::<Vec<_>>(),
+            vec![third, first, second]
+        );
+    }
 }
@@ -1,4 +1,82 @@
-use super::{geometry::GeometryControl, Client};
+use super::{
+    geometry::{ClientGeometry, GeometryControl, HorizontalResize, VerticalResize},
+    Client,
+};
+
+/// Request of _NET_WM_MOVERESIZE, defined in EWMH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveResizeRequest {
+    /// Drags with the pointer, whose button is already pressed on the app.
+    Start(GeometryControl),
+    /// Drags with the arrow keys, until Return or a click.
+    StartByKeyboard(GeometryControl),
+    Cancel,
+}
+
+impl MoveResizeRequest {
+    pub fn from_value(value: u32) -> Option<Self> {
+        use HorizontalResize as H;
+        use VerticalResize as V;
+        let resize = |vertical, horizontal| GeometryControl::Resize(vertical, horizontal);
+        Some(match value {
+            0 => MoveResizeRequest::Start(resize(V::Top, H::Left)),
+            1 => MoveResizeRequest::Start(resize(V::Top, H::None)),
+            2 => MoveResizeRequest::Start(resize(V::Top, H::Right)),
+            3 => MoveResizeRequest::Start(resize(V::None, H::Right)),
+            4 => MoveResizeRequest::Start(resize(V::Bottom, H::Right)),
+            5 => MoveResizeRequest::Start(resize(V::Bottom, H::None)),
+            6 => MoveResizeRequest::Start(resize(V::Bottom, H::Left)),
+            7 => MoveResizeRequest::Start(resize(V::None, H::Left)),
+            8 => MoveResizeRequest::Start(GeometryControl::Move),
+            9 => MoveResizeRequest::StartByKeyboard(resize(V::Bottom, H::Right)),
+            10 => MoveResizeRequest::StartByKeyboard(GeometryControl::Move),
+            11 => MoveResizeRequest::Cancel,
+            _ => return None,
+        })
+    }
+}
+
+/// Whether the button of a _NET_WM_MOVERESIZE request is held in the modifier mask of the pointer.
+/// Button 0 means any button. A request sent after the button is released must not start a drag,
+/// since no release would end it.
+pub fn is_button_held(button: u32, mask: u16) -> bool {
+    // Button1Mask to Button5Mask
+    const BUTTON1_MASK: u16 = 1 << 8;
+    const ALL_BUTTONS_MASK: u16 = 0x1F << 8;
+    match button {
+        0 => mask & ALL_BUTTONS_MASK != 0,
+        1..=5 => mask & (BUTTON1_MASK << (button - 1)) != 0,
+        _ => false,
+    }
+}
+
+/// Action of a key pressed while dragging by the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardDragAction {
+    /// Moves the pointer, which drives the drag, by the distance.
+    Step(i32, i32),
+    Confirm,
+    Cancel,
+}
+
+const KEYBOARD_DRAG_STEP: i32 = 10;
+
+impl KeyboardDragAction {
+    pub fn from_keysym(keysym: u32) -> Option<Self> {
+        match keysym {
+            // XK_Left, XK_Up, XK_Right, XK_Down
+            0xff51 => Some(KeyboardDragAction::Step(-KEYBOARD_DRAG_STEP, 0)),
+            0xff52 => Some(KeyboardDragAction::Step(0, -KEYBOARD_DRAG_STEP)),
+            0xff53 => Some(KeyboardDragAction::Step(KEYBOARD_DRAG_STEP, 0)),
+            0xff54 => Some(KeyboardDragAction::Step(0, KEYBOARD_DRAG_STEP)),
+            // XK_Return, XK_KP_Enter
+            0xff0d | 0xff8d => Some(KeyboardDragAction::Confirm),
+            // XK_Escape
+            0xff1b => Some(KeyboardDragAction::Cancel),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct DragDetail<WinId>
@@ -7,15 +85,27 @@ where
 {
     client: Client<WinId>,
     geometry_control: GeometryControl,
-    last_root_position: (i32, i32),
+    start_root_position: (i32, i32),
+    start_geometry: ClientGeometry,
 }
 
 impl<WinId> DragDetail<WinId>
 where
     WinId: Copy + Eq,
 {
-    pub fn last_root_position(&self) -> (i32, i32) {
-        self.last_root_position
+    pub fn client(&self) -> Client<WinId> {
+        self.client
+    }
+
+    pub fn start_root_position(&self) -> (i32, i32) {
+        self.start_root_position
+    }
+
+    /// The geometry of the client when the drag started.
+    /// The new geometry is computed from it rather than accumulating every motion,
+    /// so that constrained sizes do not drift away from the cursor.
+    pub fn start_geometry(&self) -> ClientGeometry {
+        self.start_geometry
     }
 
     pub fn geometry_control(&self) -> GeometryControl {
@@ -40,28 +130,28 @@ where
     pub fn new_as_dragging(
         client: Client<WinId>,
         geometry_control: GeometryControl,
-        last_root_position: (i32, i32),
+        start_root_position: (i32, i32),
+        start_geometry: ClientGeometry,
     ) -> Self {
         DragState::Dragging(DragDetail {
             client,
             geometry_control,
-            last_root_position,
+            start_root_position,
+            start_geometry,
         })
     }
 
-    pub fn change_root_position(&mut self, new_root_position: (i32, i32)) {
+    pub fn release_from_dragging(&mut self) {
         if let DragState::Dragging(drag_detail) = self {
-            *self = DragState::Dragging(DragDetail {
-                client: drag_detail.client,
-                geometry_control: drag_detail.geometry_control,
-                last_root_position: new_root_position,
-            });
+            *self = DragState::Dragged(*drag_detail);
         }
     }
 
-    pub fn release_from_dragging(&mut self) {
+    pub fn dragging(&self) -> Option<DragDetail<WinId>> {
         if let DragState::Dragging(drag_detail) = self {
-            *self = DragState::Dragged(*drag_detail);
+            Some(*drag_detail)
+        } else {
+            None
         }
     }
 
@@ -74,3 +164,70 @@ where
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_resize_request() {
+        assert_eq!(
+            MoveResizeRequest::from_value(0),
+            Some(MoveResizeRequest::Start(GeometryControl::Resize(
+                VerticalResize::Top,
+                HorizontalResize::Left
+            )))
+        );
+        assert_eq!(
+            MoveResizeRequest::from_value(3),
+            Some(MoveResizeRequest::Start(GeometryControl::Resize(
+                VerticalResize::None,
+                HorizontalResize::Right
+            )))
+        );
+        assert_eq!(
+            MoveResizeRequest::from_value(8),
+            Some(MoveResizeRequest::Start(GeometryControl::Move))
+        );
+        assert_eq!(
+            MoveResizeRequest::from_value(10),
+            Some(MoveResizeRequest::StartByKeyboard(GeometryControl::Move))
+        );
+        assert_eq!(
+            MoveResizeRequest::from_value(11),
+            Some(MoveResizeRequest::Cancel)
+        );
+        assert_eq!(MoveResizeRequest::from_value(12), None);
+    }
+
+    #[test]
+    fn test_is_button_held() {
+        let button1 = 1 << 8;
+        let button3 = 1 << 10;
+        assert!(is_button_held(1, button1));
+        assert!(!is_button_held(1, button3));
+        assert!(is_button_held(3, button1 | button3));
+        assert!(is_button_held(0, button3));
+        // a modifier such as Shift is not a button
+        assert!(!is_button_held(0, 1));
+        assert!(!is_button_held(6, button1));
+    }
+
+    #[test]
+    fn test_keyboard_drag_action() {
+        assert_eq!(
+            KeyboardDragAction::from_keysym(0xff51),
+            Some(KeyboardDragAction::Step(-KEYBOARD_DRAG_STEP, 0))
+        );
+        assert_eq!(
+            KeyboardDragAction::from_keysym(0xff0d),
+            Some(KeyboardDragAction::Confirm)
+        );
+        assert_eq!(
+            KeyboardDragAction::from_keysym(0xff1b),
+            Some(KeyboardDragAction::Cancel)
+        );
+        // XK_a
+        assert_eq!(KeyboardDragAction::from_keysym(0x61), None);
+    }
+}
@@ -0,0 +1,140 @@
+use super::Client;
+
+/// Source indication of an activation request, defined in EWMH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationSource {
+    /// Clients following an older version of the specification.
+    Legacy,
+    Application,
+    /// Pagers and taskbars, which act on behalf of the user.
+    Pager,
+}
+
+impl ActivationSource {
+    pub fn from_value(value: u32) -> Self {
+        match value {
+            1 => ActivationSource::Application,
+            2 => ActivationSource::Pager,
+            _ => ActivationSource::Legacy,
+        }
+    }
+}
+
+/// The focused client and the order in which the clients have been focused.
+pub struct FocusState<WinId>
+where
+    WinId: Copy + Eq,
+{
+    // the most recently focused client is the last
+    history: Vec<Client<WinId>>,
+    focused: Option<Client<WinId>>,
+    // X11 timestamp of the last focus change made by the user
+    last_user_time: Option<u32>,
+}
+
+impl<WinId> FocusState<WinId>
+where
+    WinId: Copy + Eq,
+{
+    pub fn new() -> Self {
+        Self {
+            history: Vec::new(),
+            focused: None,
+            last_user_time: None,
+        }
+    }
+
+    pub fn focused(&self) -> Option<Client<WinId>> {
+        self.focused
+    }
+
+    /// Focuses the client. `user_time` is given if the user has focused it, e.g. by a click.
+    pub fn focus(&mut self, client: Client<WinId>, user_time: Option<u32>) {
+        self.history.retain(|c| c != &client);
+        self.history.push(client);
+        self.focused = Some(client);
+        if user_time.is_some() {
+            self.last_user_time = user_time;
+        }
+    }
+
+    /// Forgets the client when it is removed or minimized.
+    /// Returns the client to focus instead if it was focused.
+    pub fn unfocus(&mut self, client: Client<WinId>) -> Option<Client<WinId>> {
+        self.history.retain(|c| c != &client);
+        if self.focused == Some(client) {
+            self.focused = None;
+            self.history.last().copied()
+        } else {
+            None
+        }
+    }
+
+    /// Decides whether an activation request may steal the focus.
+    /// Applications have to prove by the timestamp that the request comes after the last user action.
+    pub fn allows_activation(&self, source: ActivationSource, time: u32) -> bool {
+        match source {
+            ActivationSource::Pager | ActivationSource::Legacy => true,
+            ActivationSource::Application => match (self.focused, self.last_user_time) {
+                (None, _) | (_, None) => true,
+                // timestamps wrap around, so compare the difference
+                (Some(_), Some(last_user_time)) => {
+                    time != 0 && time.wrapping_sub(last_user_time) as i32 >= 0
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(id: u32) -> Client<u32> {
+        Client {
+            app_id: id,
+            frame_id: id + 100,
+        }
+    }
+
+    #[test]
+    fn test_focus_history() {
+        let mut state = FocusState::new();
+        state.focus(client(1), None);
+        state.focus(client(2), None);
+        state.focus(client(3), None);
+        state.focus(client(1), None);
+        assert_eq!(state.focused(), Some(client(1)));
+
+        // the previously focused client takes over the focus
+        assert_eq!(state.unfocus(client(1)), Some(client(3)));
+        assert_eq!(state.focused(), None);
+        state.focus(client(3), None);
+
+        // removing an unfocused client does not move the focus
+        assert_eq!(state.unfocus(client(2)), None);
+        assert_eq!(state.focused(), Some(client(3)));
+
+        assert_eq!(state.unfocus(client(3)), None);
+        assert_eq!(state.focused(), None);
+    }
+
+    #[test]
+    fn test_focus_stealing() {
+        let mut state = FocusState::new();
+        // nothing to protect
+        assert!(state.allows_activation(ActivationSource::Application, 0));
+
+        state.focus(client(1), Some(1000));
+        assert!(state.allows_activation(ActivationSource::Application, 1000));
+        assert!(state.allows_activation(ActivationSource::Application, 1500));
+        assert!(!state.allows_activation(ActivationSource::Application, 500));
+        assert!(!state.allows_activation(ActivationSource::Application, 0));
+        assert!(state.allows_activation(ActivationSource::Pager, 0));
+        assert!(state.allows_activation(ActivationSource::Legacy, 0));
+
+        // the timestamp wraps around
+        state.focus(client(2), Some(u32::MAX - 10));
+        assert!(state.allows_activation(ActivationSource::Application, 10));
+    }
+}
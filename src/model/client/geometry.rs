@@ -1,4 +1,6 @@
-use crate::config::FrameConfig;
+use crate::config::{FrameConfig, TitlebarButton, TitlebarConfig};
+
+use super::hints::{Gravity, SizeHints};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 
@@ -9,6 +11,35 @@ pub struct Geometry {
     pub height: u32,
 }
 
+impl Geometry {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+}
+
+/// Widths of the frame around the app on each side, published as _NET_FRAME_EXTENTS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameExtents {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl FrameExtents {
+    pub fn from_frame_config(frame_config: FrameConfig) -> FrameExtents {
+        FrameExtents {
+            left: frame_config.border_width,
+            right: frame_config.border_width,
+            top: frame_config.border_width + frame_config.titlebar_height,
+            bottom: frame_config.border_width,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ClientGeometry {
     geometry: Geometry,
@@ -60,7 +91,6 @@ impl ClientGeometry {
         }
     }
 
-    #[allow(dead_code)]
     pub fn from_frame(
         x: i32,
         y: i32,
@@ -79,6 +109,31 @@ impl ClientGeometry {
         }
     }
 
+    /// Builds the geometry of the app placed by the client as if it had no frame.
+    /// The frame is added around it keeping the reference point of the gravity, as described in ICCCM 4.1.2.3.
+    pub fn from_unframed_app(
+        app_geometry: Geometry,
+        gravity: Gravity,
+        frame_config: FrameConfig,
+    ) -> ClientGeometry {
+        let extents = FrameExtents::from_frame_config(frame_config);
+        let (x, y) = if let Some((x_halves, y_halves)) = gravity.reference_point() {
+            let extra_width = (extents.left + extents.right) as i32;
+            let extra_height = (extents.top + extents.bottom) as i32;
+            (
+                app_geometry.x - extra_width * x_halves / 2 + extents.left as i32,
+                app_geometry.y - extra_height * y_halves / 2 + extents.top as i32,
+            )
+        } else {
+            (app_geometry.x, app_geometry.y)
+        };
+        ClientGeometry::from_app(x, y, app_geometry.width, app_geometry.height, frame_config)
+    }
+
+    pub fn frame_extents(&self) -> FrameExtents {
+        FrameExtents::from_frame_config(self.frame_config)
+    }
+
     pub fn parse_as_app(&self) -> Geometry {
         Geometry {
             x: self.geometry.x,
@@ -127,6 +182,163 @@ impl ClientGeometry {
         }
     }
 
+    /// Builds the geometry of a maximized client, whose visible frame covers the area.
+    /// The transparent border around the frame is placed outside of the area.
+    pub fn maximized_in(area: Geometry, frame_config: FrameConfig) -> ClientGeometry {
+        ClientGeometry::from_frame(
+            area.x - frame_config.border_width as i32,
+            area.y - frame_config.border_width as i32,
+            area.width + 2 * frame_config.border_width,
+            area.height + 2 * frame_config.border_width,
+            frame_config,
+        )
+    }
+
+    /// Maximizes the client only in the given directions, keeping the rest of the geometry.
+    pub fn maximized_on_axes(
+        &self,
+        area: Geometry,
+        horizontal: bool,
+        vertical: bool,
+    ) -> ClientGeometry {
+        let maximized = ClientGeometry::maximized_in(area, self.frame_config).geometry;
+        let (x, width) = if horizontal {
+            (maximized.x, maximized.width)
+        } else {
+            (self.geometry.x, self.geometry.width)
+        };
+        let (y, height) = if vertical {
+            (maximized.y, maximized.height)
+        } else {
+            (self.geometry.y, self.geometry.height)
+        };
+        ClientGeometry::from_app(x, y, width, height, self.frame_config)
+    }
+
+    /// Builds the geometry of a fullscreen client, whose app covers the area without decorations.
+    pub fn fullscreen_in(area: Geometry, frame_config: FrameConfig) -> ClientGeometry {
+        ClientGeometry::from_app(
+            area.x,
+            area.y,
+            area.width,
+            area.height,
+            frame_config.frameless(),
+        )
+    }
+
+    /// Moves the client so that its frame is at the center of the area.
+    pub fn centered_over(&self, area: Geometry) -> ClientGeometry {
+        let frame_geometry = self.parse_as_frame();
+        let x = area.x + (area.width as i32 - frame_geometry.width as i32) / 2;
+        let y = area.y + (area.height as i32 - frame_geometry.height as i32) / 2;
+        self.move_relative(x - frame_geometry.x, y - frame_geometry.y)
+    }
+
+    /// Moves the client so that its visible frame is inside the area.
+    /// The client larger than the area is aligned to the top left of the area.
+    pub fn clamped_into(&self, area: Geometry) -> ClientGeometry {
+        let border_width = self.frame_config.border_width as i32;
+        let frame_geometry = self.parse_as_frame();
+        let visible_x = frame_geometry.x + border_width;
+        let visible_y = frame_geometry.y + border_width;
+        let clamp = |position: i32, length: u32, area_start: i32, area_length: u32| {
+            let overflow = area_length as i32 - length as i32;
+            if overflow <= 0 {
+                area_start
+            } else {
+                position.clamp(area_start, area_start + overflow)
+            }
+        };
+        let x = clamp(
+            visible_x,
+            frame_geometry.width - 2 * self.frame_config.border_width,
+            area.x,
+            area.width,
+        );
+        let y = clamp(
+            visible_y,
+            frame_geometry.height - 2 * self.frame_config.border_width,
+            area.y,
+            area.height,
+        );
+        self.move_relative(x - visible_x, y - visible_y)
+    }
+
+    /// The frame of a shaded client, which shows only the titlebar.
+    pub fn parse_as_shaded_frame(&self) -> Geometry {
+        Geometry {
+            height: 2 * self.frame_config.border_width + self.frame_config.titlebar_height,
+            ..self.parse_as_frame()
+        }
+    }
+
+    /// Rebuilds the geometry for another frame configuration, keeping the application area as it is.
+    pub fn with_frame_config(&self, frame_config: FrameConfig) -> ClientGeometry {
+        ClientGeometry {
+            geometry: self.geometry,
+            frame_config,
+        }
+    }
+
+    /// Square areas of the titlebar buttons, in the coordinates of the frame.
+    pub fn parse_as_button_draw_areas(
+        &self,
+        titlebar_config: &TitlebarConfig,
+    ) -> Vec<(TitlebarButton, Geometry)> {
+        let inner_frame = self.parse_as_inner_frame_draw_area();
+        let size = self.frame_config.titlebar_height;
+        let button_area = |x: i32| Geometry {
+            x,
+            y: inner_frame.y,
+            width: size,
+            height: size,
+        };
+
+        let left = titlebar_config.left.iter().enumerate().map(|(i, button)| {
+            (
+                *button,
+                button_area(inner_frame.x + (i as u32 * size) as i32),
+            )
+        });
+
+        let right_end = inner_frame.x + inner_frame.width as i32;
+        let right_count = titlebar_config.right.len();
+        let right = titlebar_config.right.iter().enumerate().map(|(i, button)| {
+            (
+                *button,
+                button_area(right_end - ((right_count - i) as u32 * size) as i32),
+            )
+        });
+
+        left.chain(right).collect()
+    }
+
+    /// The area of the titlebar between the buttons, in the coordinates of the frame.
+    pub fn parse_as_title_draw_area(&self, titlebar_config: &TitlebarConfig) -> Geometry {
+        let inner_frame = self.parse_as_inner_frame_draw_area();
+        let size = self.frame_config.titlebar_height;
+        Geometry {
+            x: inner_frame.x + (titlebar_config.left.len() as u32 * size) as i32,
+            y: inner_frame.y,
+            width: inner_frame.width.saturating_sub(
+                (titlebar_config.left.len() + titlebar_config.right.len()) as u32 * size,
+            ),
+            height: size,
+        }
+    }
+
+    pub fn check_button_by_position_on_frame(
+        &self,
+        x_on_frame: i32,
+        y_on_frame: i32,
+        titlebar_config: &TitlebarConfig,
+    ) -> Option<TitlebarButton> {
+        self.parse_as_button_draw_areas(titlebar_config)
+            .into_iter()
+            .find(|(_, area)| area.contains(x_on_frame, y_on_frame))
+            .map(|(button, _)| button)
+    }
+
     pub fn move_relative(&self, rel_x: i32, rel_y: i32) -> ClientGeometry {
         ClientGeometry {
             geometry: Geometry {
@@ -210,35 +422,80 @@ impl ClientGeometry {
         cursor_move_x: i32,
         cursor_move_y: i32,
         control: GeometryControl,
+        size_hints: &SizeHints,
     ) -> ClientGeometry {
         match control {
             GeometryControl::Move => self.move_relative(cursor_move_x, cursor_move_y),
             GeometryControl::Resize(vertical, horizontal) => {
-                let mut new_geom = *self;
-                match vertical {
-                    VerticalResize::Top => {
-                        new_geom.geometry.y += cursor_move_y;
-                        new_geom.geometry.height -= cursor_move_y as u32;
-                    }
-                    VerticalResize::Bottom => {
-                        new_geom.geometry.height += cursor_move_y as u32;
-                    }
-                    VerticalResize::None => {}
-                }
-                match horizontal {
-                    HorizontalResize::Left => {
-                        new_geom.geometry.x += cursor_move_x;
-                        new_geom.geometry.width -= cursor_move_x as u32;
-                    }
-                    HorizontalResize::Right => {
-                        new_geom.geometry.width += cursor_move_x as u32;
-                    }
-                    HorizontalResize::None => {}
+                let geometry = self.geometry;
+                let requested_width = match horizontal {
+                    HorizontalResize::Left => geometry.width as i64 - cursor_move_x as i64,
+                    HorizontalResize::Right => geometry.width as i64 + cursor_move_x as i64,
+                    HorizontalResize::None => geometry.width as i64,
+                };
+                let requested_height = match vertical {
+                    VerticalResize::Top => geometry.height as i64 - cursor_move_y as i64,
+                    VerticalResize::Bottom => geometry.height as i64 + cursor_move_y as i64,
+                    VerticalResize::None => geometry.height as i64,
+                };
+                let (width, height) =
+                    self.constrain_size(requested_width, requested_height, size_hints);
+
+                // keep the opposite edge fixed
+                let x = match horizontal {
+                    HorizontalResize::Left => geometry.x + geometry.width as i32 - width as i32,
+                    _ => geometry.x,
+                };
+                let y = match vertical {
+                    VerticalResize::Top => geometry.y + geometry.height as i32 - height as i32,
+                    _ => geometry.y,
+                };
+
+                ClientGeometry {
+                    geometry: Geometry {
+                        x,
+                        y,
+                        width,
+                        height,
+                    },
+                    frame_config: self.frame_config,
                 }
-                new_geom
             }
         }
     }
+
+    /// The geometry of the app after the frame is removed, keeping the reference point of the gravity
+    /// as described in ICCCM 4.1.2.3.
+    pub fn parse_as_unframed_app(&self, gravity: Gravity) -> Geometry {
+        let app_geometry = self.parse_as_app();
+        let (x, y) = if let Some((x_halves, y_halves)) = gravity.reference_point() {
+            let frame_geometry = self.parse_as_frame();
+            let extra_width = frame_geometry.width as i32 - app_geometry.width as i32;
+            let extra_height = frame_geometry.height as i32 - app_geometry.height as i32;
+            (
+                frame_geometry.x + extra_width * x_halves / 2,
+                frame_geometry.y + extra_height * y_halves / 2,
+            )
+        } else {
+            (app_geometry.x, app_geometry.y)
+        };
+        Geometry {
+            x,
+            y,
+            ..app_geometry
+        }
+    }
+
+    /// Constrains the size of the application by its size hints and the minimum frame size.
+    pub fn constrain_size(&self, width: i64, height: i64, size_hints: &SizeHints) -> (u32, u32) {
+        let wm_min_size = (
+            self.frame_config.min_width,
+            self.frame_config
+                .min_height
+                .saturating_sub(self.frame_config.titlebar_height),
+        );
+        size_hints.constrain(width, height, wm_min_size)
+    }
 }
 
 impl GeometryControl {
@@ -257,6 +514,8 @@ mod tests {
             border_width: 4,
             titlebar_height: 20,
             corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
         };
         let client_geom = ClientGeometry::from_app(0, 0, 100, 100, frame_config);
 
@@ -291,6 +550,392 @@ mod tests {
             client_geom.move_relative(10, 10),
             ClientGeometry::from_frame(10, 10, 100, 100, frame_config)
         );
+
+        let wider_frame_config = FrameConfig {
+            border_width: 8,
+            titlebar_height: 20,
+            corner_radius: 8,
+            min_width: 40,
+            min_height: 40,
+        };
+        let client_geom = client_geom.with_frame_config(wider_frame_config);
+
+        assert_eq!(
+            client_geom.parse_as_app(),
+            Geometry {
+                x: 4,
+                y: 24,
+                width: 92,
+                height: 72
+            }
+        );
+
+        assert_eq!(
+            client_geom.parse_as_frame(),
+            Geometry {
+                x: -4,
+                y: -4,
+                width: 108,
+                height: 108
+            }
+        );
+    }
+
+    #[test]
+    fn test_unframed_app() {
+        let frame_config = FrameConfig {
+            border_width: 4,
+            titlebar_height: 20,
+            corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
+        };
+        let client_geom = ClientGeometry::from_app(100, 100, 200, 150, frame_config);
+        let unframed = |gravity| {
+            let geometry = client_geom.parse_as_unframed_app(gravity);
+            (geometry.x, geometry.y)
+        };
+
+        // the frame is at (96, 76) with the size of 208x178
+        assert_eq!(unframed(Gravity::NorthWest), (96, 76));
+        assert_eq!(unframed(Gravity::Center), (100, 90));
+        assert_eq!(unframed(Gravity::SouthEast), (104, 104));
+        assert_eq!(unframed(Gravity::Static), (100, 100));
+        assert_eq!(client_geom.parse_as_unframed_app(Gravity::North).width, 200);
+
+        // the frame is added back at the same place
+        for gravity in [
+            Gravity::NorthWest,
+            Gravity::North,
+            Gravity::Center,
+            Gravity::SouthEast,
+            Gravity::Static,
+        ] {
+            assert_eq!(
+                ClientGeometry::from_unframed_app(
+                    client_geom.parse_as_unframed_app(gravity),
+                    gravity,
+                    frame_config
+                ),
+                client_geom
+            );
+        }
+
+        assert_eq!(
+            client_geom.frame_extents(),
+            FrameExtents {
+                left: 4,
+                right: 4,
+                top: 24,
+                bottom: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_maximized() {
+        let frame_config = FrameConfig {
+            border_width: 4,
+            titlebar_height: 20,
+            corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
+        };
+        let area = Geometry {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        };
+        let client_geom = ClientGeometry::maximized_in(area, frame_config);
+
+        assert_eq!(
+            client_geom.parse_as_app(),
+            Geometry {
+                x: 0,
+                y: 20,
+                width: 1920,
+                height: 1060
+            }
+        );
+    }
+
+    #[test]
+    fn test_window_states() {
+        let frame_config = FrameConfig {
+            border_width: 4,
+            titlebar_height: 20,
+            corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
+        };
+        let area = Geometry {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        };
+        let client_geom = ClientGeometry::from_app(100, 100, 300, 200, frame_config);
+
+        assert_eq!(
+            client_geom
+                .maximized_on_axes(area, false, true)
+                .parse_as_app(),
+            Geometry {
+                x: 100,
+                y: 20,
+                width: 300,
+                height: 1060
+            }
+        );
+        assert_eq!(
+            client_geom
+                .maximized_on_axes(area, true, false)
+                .parse_as_app(),
+            Geometry {
+                x: 0,
+                y: 100,
+                width: 1920,
+                height: 200
+            }
+        );
+        assert_eq!(
+            client_geom.maximized_on_axes(area, true, true),
+            ClientGeometry::maximized_in(area, frame_config)
+        );
+
+        let fullscreen = ClientGeometry::fullscreen_in(area, frame_config);
+        assert_eq!(fullscreen.parse_as_app(), area);
+        assert_eq!(fullscreen.parse_as_frame(), area);
+
+        assert_eq!(
+            client_geom.parse_as_shaded_frame(),
+            Geometry {
+                x: 96,
+                y: 76,
+                width: 308,
+                height: 28
+            }
+        );
+    }
+
+    #[test]
+    fn test_centered_over() {
+        let frame_config = FrameConfig {
+            border_width: 4,
+            titlebar_height: 20,
+            corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
+        };
+        let parent = Geometry {
+            x: 100,
+            y: 50,
+            width: 1000,
+            height: 800,
+        };
+        let client_geom = ClientGeometry::from_app(0, 0, 300, 200, frame_config);
+
+        assert_eq!(
+            client_geom.centered_over(parent).parse_as_frame(),
+            Geometry {
+                x: 446,
+                y: 336,
+                width: 308,
+                height: 228
+            }
+        );
+
+        // the frameless client is centered by the app itself
+        let client_geom = ClientGeometry::from_app(0, 0, 300, 200, frame_config.frameless());
+        assert_eq!(
+            client_geom.centered_over(parent).parse_as_app(),
+            Geometry {
+                x: 450,
+                y: 350,
+                width: 300,
+                height: 200
+            }
+        );
+    }
+
+    #[test]
+    fn test_clamped_into() {
+        let frame_config = FrameConfig {
+            border_width: 4,
+            titlebar_height: 20,
+            corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
+        };
+        let work_area = Geometry {
+            x: 0,
+            y: 30,
+            width: 1920,
+            height: 1010,
+        };
+
+        // the titlebar under the top panel is moved below it
+        let client_geom = ClientGeometry::from_app(100, 20, 300, 200, frame_config);
+        assert_eq!(
+            client_geom.clamped_into(work_area).parse_as_app(),
+            Geometry {
+                x: 100,
+                y: 50,
+                width: 300,
+                height: 200
+            }
+        );
+
+        // the client inside the area is not moved
+        let client_geom = ClientGeometry::from_app(100, 100, 300, 200, frame_config);
+        assert_eq!(client_geom.clamped_into(work_area), client_geom);
+
+        // the client beyond the bottom right is moved back
+        let client_geom = ClientGeometry::from_app(1800, 1000, 300, 200, frame_config);
+        assert_eq!(
+            client_geom.clamped_into(work_area).parse_as_app(),
+            Geometry {
+                x: 1620,
+                y: 840,
+                width: 300,
+                height: 200
+            }
+        );
+
+        // the client larger than the area is aligned to the top left
+        let client_geom = ClientGeometry::from_app(100, 100, 2000, 1200, frame_config);
+        assert_eq!(
+            client_geom.clamped_into(work_area).parse_as_frame(),
+            Geometry {
+                x: -4,
+                y: 26,
+                width: 2008,
+                height: 1228
+            }
+        );
+    }
+
+    #[test]
+    fn test_titlebar_buttons() {
+        let frame_config = FrameConfig {
+            border_width: 4,
+            titlebar_height: 20,
+            corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
+        };
+        let titlebar_config = TitlebarConfig {
+            left: vec![TitlebarButton::Close],
+            right: vec![TitlebarButton::Minimize, TitlebarButton::Maximize],
+        };
+        let client_geom = ClientGeometry::from_app(0, 0, 100, 100, frame_config);
+
+        assert_eq!(
+            client_geom.parse_as_button_draw_areas(&titlebar_config),
+            vec![
+                (
+                    TitlebarButton::Close,
+                    Geometry {
+                        x: 4,
+                        y: 4,
+                        width: 20,
+                        height: 20
+                    }
+                ),
+                (
+                    TitlebarButton::Minimize,
+                    Geometry {
+                        x: 64,
+                        y: 4,
+                        width: 20,
+                        height: 20
+                    }
+                ),
+                (
+                    TitlebarButton::Maximize,
+                    Geometry {
+                        x: 84,
+                        y: 4,
+                        width: 20,
+                        height: 20
+                    }
+                ),
+            ]
+        );
+
+        assert_eq!(
+            client_geom.parse_as_title_draw_area(&titlebar_config),
+            Geometry {
+                x: 24,
+                y: 4,
+                width: 40,
+                height: 20
+            }
+        );
+
+        assert_eq!(
+            client_geom.check_button_by_position_on_frame(10, 10, &titlebar_config),
+            Some(TitlebarButton::Close)
+        );
+        assert_eq!(
+            client_geom.check_button_by_position_on_frame(103, 23, &titlebar_config),
+            Some(TitlebarButton::Maximize)
+        );
+        // on the title
+        assert_eq!(
+            client_geom.check_button_by_position_on_frame(40, 10, &titlebar_config),
+            None
+        );
+        // on the border above the buttons
+        assert_eq!(
+            client_geom.check_button_by_position_on_frame(10, 2, &titlebar_config),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resize_on_control() {
+        let frame_config = FrameConfig {
+            border_width: 4,
+            titlebar_height: 20,
+            corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
+        };
+        let client_geom = ClientGeometry::from_app(0, 0, 100, 100, frame_config);
+        let size_hints = SizeHints::default();
+        let top_left = GeometryControl::Resize(VerticalResize::Top, HorizontalResize::Left);
+        let bottom_right = GeometryControl::Resize(VerticalResize::Bottom, HorizontalResize::Right);
+
+        assert_eq!(
+            client_geom.move_resize_on_control(10, 20, top_left, &size_hints),
+            ClientGeometry::from_app(10, 20, 90, 80, frame_config)
+        );
+        assert_eq!(
+            client_geom.move_resize_on_control(10, 20, bottom_right, &size_hints),
+            ClientGeometry::from_app(0, 0, 110, 120, frame_config)
+        );
+
+        // dragging the top left corner past the bottom right keeps the bottom right fixed
+        assert_eq!(
+            client_geom.move_resize_on_control(300, 300, top_left, &size_hints),
+            ClientGeometry::from_app(60, 80, 40, 20, frame_config)
+        );
+        assert_eq!(
+            client_geom.move_resize_on_control(-300, -300, bottom_right, &size_hints),
+            ClientGeometry::from_app(0, 0, 40, 20, frame_config)
+        );
+
+        let size_hints = SizeHints {
+            max_size: Some((150, 150)),
+            size_increment: Some((10, 10)),
+            ..Default::default()
+        };
+        assert_eq!(
+            client_geom.move_resize_on_control(-95, -25, top_left, &size_hints),
+            ClientGeometry::from_app(-50, -20, 150, 120, frame_config)
+        );
     }
 
     #[test]
@@ -299,6 +944,8 @@ mod tests {
             border_width: 4,
             titlebar_height: 20,
             corner_radius: 6,
+            min_width: 40,
+            min_height: 40,
         };
         let client_geom = ClientGeometry::from_app(0, 0, 100, 100, frame_config);
 
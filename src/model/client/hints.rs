@@ -1,4 +1,268 @@
+/// Properties set by the client on its window, refreshed when they change.
 #[derive(Debug, Clone, Default)]
-pub struct ClientHints {
+pub struct ClientHints<WinId> {
     pub title: String,
+    pub size_hints: SizeHints,
+    pub class: Option<WindowClass>,
+    pub wm_hints: WmHints<WinId>,
+    /// The window this one is a dialog or a popup of, from WM_TRANSIENT_FOR.
+    pub transient_for: Option<WinId>,
+    /// The host the client runs on, from WM_CLIENT_MACHINE.
+    pub client_machine: Option<String>,
+    /// The process id in _NET_WM_PID, meaningful only on the client machine.
+    pub pid: Option<u32>,
+    /// WM_WINDOW_ROLE, which tells apart the windows of the same class.
+    pub role: Option<String>,
+    /// The icons in _NET_WM_ICON, in the sizes given by the client.
+    pub icons: Vec<Icon>,
+    /// The protocols in WM_PROTOCOLS known to the window manager.
+    pub protocols: Vec<Protocol>,
+}
+
+impl<WinId> ClientHints<WinId> {
+    pub fn supports(&self, protocol: Protocol) -> bool {
+        self.protocols.contains(&protocol)
+    }
+}
+
+/// A protocol the client takes part in, listed in WM_PROTOCOLS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// WM_DELETE_WINDOW, to ask the client to close.
+    DeleteWindow,
+    /// _NET_WM_PING, to check whether the client is responding.
+    Ping,
+    /// _NET_WM_SYNC_REQUEST, to wait for the client to repaint after a resize.
+    SyncRequest,
+}
+
+/// Instance and class names in WM_CLASS, which identify the app in window rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowClass {
+    pub instance: String,
+    pub class: String,
+}
+
+/// WM_HINTS described in ICCCM 4.1.2.4, without the legacy icon fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WmHints<WinId> {
+    /// Whether the client relies on the window manager to get the input focus.
+    pub input: Option<bool>,
+    pub initial_state: Option<InitialState>,
+    pub urgent: bool,
+    /// The leader of the windows of the app.
+    pub window_group: Option<WinId>,
+}
+
+/// The state the client asks for when its window is mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialState {
+    Normal,
+    Iconic,
+}
+
+/// An icon in _NET_WM_ICON, with ARGB pixels in rows from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+impl Icon {
+    /// Parses _NET_WM_ICON, which is a list of the width, the height and the pixels of each icon.
+    /// The icons after a truncated one are dropped.
+    pub fn parse_all(values: &[u32]) -> Vec<Icon> {
+        let mut icons = Vec::new();
+        let mut rest = values;
+        while let [width, height, pixels @ ..] = rest {
+            let length = (*width as usize).saturating_mul(*height as usize);
+            if length == 0 || length > pixels.len() {
+                break;
+            }
+            icons.push(Icon {
+                width: *width,
+                height: *height,
+                pixels: pixels[..length].to_vec(),
+            });
+            rest = &pixels[length..];
+        }
+        icons
+    }
+}
+
+/// Size constraints of the application window, from WM_NORMAL_HINTS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeHints {
+    pub min_size: Option<(u32, u32)>,
+    pub max_size: Option<(u32, u32)>,
+    pub base_size: Option<(u32, u32)>,
+    pub size_increment: Option<(u32, u32)>,
+    /// Minimum and maximum aspect ratios as `(numerator, denominator)`.
+    pub aspect: Option<((u32, u32), (u32, u32))>,
+    pub gravity: Gravity,
+}
+
+/// Window gravity, the point of the window kept in place when the frame is added or removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Gravity {
+    #[default]
+    NorthWest,
+    North,
+    NorthEast,
+    West,
+    Center,
+    East,
+    SouthWest,
+    South,
+    SouthEast,
+    /// The app itself stays in place.
+    Static,
+}
+
+impl Gravity {
+    /// Position of the reference point in halves of the width and height,
+    /// or None for the static gravity.
+    pub fn reference_point(&self) -> Option<(i32, i32)> {
+        match self {
+            Gravity::NorthWest => Some((0, 0)),
+            Gravity::North => Some((1, 0)),
+            Gravity::NorthEast => Some((2, 0)),
+            Gravity::West => Some((0, 1)),
+            Gravity::Center => Some((1, 1)),
+            Gravity::East => Some((2, 1)),
+            Gravity::SouthWest => Some((0, 2)),
+            Gravity::South => Some((1, 2)),
+            Gravity::SouthEast => Some((2, 2)),
+            Gravity::Static => None,
+        }
+    }
+}
+
+impl SizeHints {
+    /// Constrains the requested size of the application window as described in ICCCM 4.1.2.3.
+    /// `wm_min_size` is the smallest size allowed by the window manager regardless of the hints.
+    pub fn constrain(&self, width: i64, height: i64, wm_min_size: (u32, u32)) -> (u32, u32) {
+        // the base size is used as the minimum size if the minimum size is not given, and vice versa
+        let min_size = self.min_size.or(self.base_size).unwrap_or((1, 1));
+        let base_size = self.base_size.or(self.min_size).unwrap_or((0, 0));
+
+        let min_width = (min_size.0.max(wm_min_size.0).max(1)) as i64;
+        let min_height = (min_size.1.max(wm_min_size.1).max(1)) as i64;
+        let (max_width, max_height) = self
+            .max_size
+            .map(|(w, h)| ((w as i64).max(min_width), (h as i64).max(min_height)))
+            .unwrap_or((i64::MAX, i64::MAX));
+
+        let mut width = width.clamp(min_width, max_width);
+        let mut height = height.clamp(min_height, max_height);
+
+        if let Some(((min_x, min_y), (max_x, max_y))) = self.aspect {
+            let (base_width, base_height) = (base_size.0 as i64, base_size.1 as i64);
+            let aspect_width = width - base_width;
+            let aspect_height = height - base_height;
+            if min_y > 0 && aspect_width * (min_y as i64) < aspect_height * (min_x as i64) {
+                // too tall
+                if min_x > 0 {
+                    height = base_height + aspect_width * (min_y as i64) / (min_x as i64);
+                }
+            } else if max_y > 0 && aspect_width * (max_y as i64) > aspect_height * (max_x as i64) {
+                // too wide
+                width = base_width + aspect_height * (max_x as i64) / (max_y as i64);
+            }
+            width = width.clamp(min_width, max_width);
+            height = height.clamp(min_height, max_height);
+        }
+
+        if let Some((increment_width, increment_height)) = self.size_increment {
+            let step = |size: i64, base: i64, increment: i64, min: i64| {
+                if increment <= 1 || size < base {
+                    return size;
+                }
+                let size = base + (size - base) / increment * increment;
+                if size < min {
+                    size + increment
+                } else {
+                    size
+                }
+            };
+            width = step(width, base_size.0 as i64, increment_width as i64, min_width);
+            height = step(
+                height,
+                base_size.1 as i64,
+                increment_height as i64,
+                min_height,
+            );
+        }
+
+        (width as u32, height as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_icons() {
+        let values = [2, 1, 0xFF000000, 0xFFFFFFFF, 1, 1, 0x80FF0000, 4, 4, 0];
+        let icons = Icon::parse_all(&values);
+        // the truncated 4x4 icon is dropped
+        assert_eq!(icons.len(), 2);
+        assert_eq!(icons[0].pixels, vec![0xFF000000, 0xFFFFFFFF]);
+        assert_eq!(icons[1].width, 1);
+        assert!(Icon::parse_all(&[0, 0, 1]).is_empty());
+    }
+
+    #[test]
+    fn test_min_max_size() {
+        let size_hints = SizeHints {
+            min_size: Some((100, 50)),
+            max_size: Some((400, 300)),
+            ..Default::default()
+        };
+        assert_eq!(size_hints.constrain(200, 200, (0, 0)), (200, 200));
+        assert_eq!(size_hints.constrain(-30, 20, (0, 0)), (100, 50));
+        assert_eq!(size_hints.constrain(500, 500, (0, 0)), (400, 300));
+
+        // the minimum size of the window manager wins
+        assert_eq!(size_hints.constrain(10, 10, (120, 40)), (120, 50));
+
+        // no hints
+        assert_eq!(SizeHints::default().constrain(-5, -5, (0, 0)), (1, 1));
+    }
+
+    #[test]
+    fn test_size_increment() {
+        // like a terminal with 8x16 cells
+        let size_hints = SizeHints {
+            base_size: Some((4, 4)),
+            size_increment: Some((8, 16)),
+            ..Default::default()
+        };
+        assert_eq!(
+            size_hints.constrain(4 + 8 * 80 + 5, 4 + 16 * 24 + 15, (0, 0)),
+            (644, 388)
+        );
+        assert_eq!(
+            size_hints.constrain(4 + 8 * 80, 4 + 16 * 24, (0, 0)),
+            (644, 388)
+        );
+
+        // rounded up to keep the minimum size
+        assert_eq!(size_hints.constrain(10, 10, (30, 30)), (36, 36));
+    }
+
+    #[test]
+    fn test_aspect() {
+        let size_hints = SizeHints {
+            aspect: Some(((1, 1), (2, 1))),
+            ..Default::default()
+        };
+        assert_eq!(size_hints.constrain(300, 200, (0, 0)), (300, 200));
+        // too tall
+        assert_eq!(size_hints.constrain(100, 300, (0, 0)), (100, 100));
+        // too wide
+        assert_eq!(size_hints.constrain(500, 100, (0, 0)), (200, 100));
+    }
 }
@@ -31,6 +31,13 @@ where
             .map(|(_, item)| item)
     }
 
+    pub fn query_mut(&mut self, client: Client<WinId>) -> Option<&mut T> {
+        self.item
+            .iter_mut()
+            .find(|(c, _)| c == &client)
+            .map(|(_, item)| item)
+    }
+
     pub fn remove(&mut self, client: Client<WinId>) {
         self.item.retain(|(c, _)| c != &client);
     }
@@ -99,4 +106,26 @@ mod tests {
             Some(&6)
         );
     }
+
+    #[test]
+    fn test_query_mut() {
+        let client = Client {
+            app_id: 1,
+            frame_id: 2,
+        };
+        let mut map = ClientMap::new();
+        map.insert(client, 3);
+
+        if let Some(item) = map.query_mut(client) {
+            *item = 4;
+        }
+        assert_eq!(map.query(client), Some(&4));
+        assert_eq!(
+            map.query_mut(Client {
+                app_id: 5,
+                frame_id: 6
+            }),
+            None
+        );
+    }
 }
@@ -1,8 +1,17 @@
 pub mod container;
 pub mod drag;
+pub mod focus;
 pub mod geometry;
 pub mod hints;
 pub mod map;
+pub mod ping;
+pub mod restart;
+pub mod state;
+pub mod strut;
+pub mod sync;
+pub mod text;
+pub mod titlebar;
+pub mod window_type;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Client<WinId>
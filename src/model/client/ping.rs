@@ -0,0 +1,98 @@
+/// Liveness of a client supporting _NET_WM_PING.
+/// A client is shown as not responding when it has not answered a ping in time,
+/// and is back to normal when it answers any ping later.
+/// The timeout itself is a timer of the window manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingState {
+    responding: bool,
+    // asked to close, and not answered any ping since then
+    close_requested: bool,
+}
+
+impl Default for PingState {
+    fn default() -> Self {
+        Self {
+            responding: true,
+            close_requested: false,
+        }
+    }
+}
+
+impl PingState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_responding(&self) -> bool {
+        self.responding
+    }
+
+    /// Handles the reply to a ping. Returns true if the client has come back.
+    pub fn pong(&mut self) -> bool {
+        // the client has handled the close request, even if it chose not to close
+        self.close_requested = false;
+        let changed = !self.responding;
+        self.responding = true;
+        changed
+    }
+
+    /// Records a request to close the client. Returns true if the client should be killed,
+    /// as it has been asked to close before and has stopped responding since then.
+    pub fn request_close(&mut self) -> bool {
+        let kill = self.close_requested && !self.responding;
+        self.close_requested = true;
+        kill
+    }
+
+    /// Marks the client as not responding when a ping has not been answered in time.
+    /// Returns true if the client has stopped responding.
+    pub fn time_out(&mut self) -> bool {
+        let changed = self.responding;
+        self.responding = false;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_state() {
+        let mut state = PingState::new();
+        assert!(state.is_responding());
+        assert!(!state.pong());
+        assert!(state.is_responding());
+
+        assert!(state.time_out());
+        assert!(!state.is_responding());
+        // no change is reported while the client keeps hanging
+        assert!(!state.time_out());
+        assert!(!state.is_responding());
+
+        assert!(state.pong());
+        assert!(state.is_responding());
+    }
+
+    #[test]
+    fn test_request_close() {
+        let mut state = PingState::new();
+        // the first request is always sent to the client
+        assert!(!state.request_close());
+        assert!(state.time_out());
+        assert!(state.request_close());
+
+        // a client hung before the first request is asked to close first
+        let mut state = PingState::new();
+        state.time_out();
+        assert!(!state.request_close());
+        assert!(state.request_close());
+
+        // a client which answered after the request is asked again
+        let mut state = PingState::new();
+        assert!(!state.request_close());
+        state.pong();
+        state.time_out();
+        assert!(!state.request_close());
+    }
+}
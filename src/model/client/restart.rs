@@ -0,0 +1,112 @@
+use super::geometry::Geometry;
+
+/// State of a client kept across an in-place restart.
+/// It is stored on the app window as an array of 32-bit values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartState {
+    /// Geometry of the app on the root.
+    pub geometry: Geometry,
+    /// Geometry of the app before it was maximized.
+    pub restore_geometry: Option<Geometry>,
+    pub minimized: bool,
+    pub focused: bool,
+}
+
+const VERSION: u32 = 1;
+
+const MAXIMIZED: u32 = 1 << 0;
+const MINIMIZED: u32 = 1 << 1;
+const FOCUSED: u32 = 1 << 2;
+
+impl RestartState {
+    pub fn encode(&self) -> Vec<u32> {
+        let mut flags = 0;
+        if self.restore_geometry.is_some() {
+            flags |= MAXIMIZED;
+        }
+        if self.minimized {
+            flags |= MINIMIZED;
+        }
+        if self.focused {
+            flags |= FOCUSED;
+        }
+        let restore_geometry = self.restore_geometry.unwrap_or(self.geometry);
+
+        let mut values = vec![VERSION, flags];
+        values.extend(encode_geometry(&self.geometry));
+        values.extend(encode_geometry(&restore_geometry));
+        values
+    }
+
+    /// Returns None if the values are written by an incompatible version.
+    pub fn decode(values: &[u32]) -> Option<Self> {
+        if values.len() != 10 || values[0] != VERSION {
+            return None;
+        }
+        let flags = values[1];
+        Some(Self {
+            geometry: decode_geometry(&values[2..6]),
+            restore_geometry: (flags & MAXIMIZED != 0).then(|| decode_geometry(&values[6..10])),
+            minimized: flags & MINIMIZED != 0,
+            focused: flags & FOCUSED != 0,
+        })
+    }
+}
+
+fn encode_geometry(geometry: &Geometry) -> [u32; 4] {
+    [
+        geometry.x as u32,
+        geometry.y as u32,
+        geometry.width,
+        geometry.height,
+    ]
+}
+
+fn decode_geometry(values: &[u32]) -> Geometry {
+    Geometry {
+        x: values[0] as i32,
+        y: values[1] as i32,
+        width: values[2],
+        height: values[3],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_state() {
+        let state = RestartState {
+            geometry: Geometry {
+                x: -10,
+                y: 20,
+                width: 300,
+                height: 200,
+            },
+            restore_geometry: Some(Geometry {
+                x: 5,
+                y: 6,
+                width: 7,
+                height: 8,
+            }),
+            minimized: false,
+            focused: true,
+        };
+        assert_eq!(RestartState::decode(&state.encode()), Some(state));
+
+        let state = RestartState {
+            restore_geometry: None,
+            minimized: true,
+            focused: false,
+            ..state
+        };
+        assert_eq!(RestartState::decode(&state.encode()), Some(state));
+
+        // written by another version
+        let mut values = state.encode();
+        values[0] = VERSION + 1;
+        assert_eq!(RestartState::decode(&values), None);
+        assert_eq!(RestartState::decode(&[]), None);
+    }
+}
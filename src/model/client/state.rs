@@ -0,0 +1,189 @@
+/// A state of the client in _NET_WM_STATE, defined in EWMH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateFlag {
+    Fullscreen,
+    MaximizedVert,
+    MaximizedHorz,
+    Above,
+    Below,
+    Sticky,
+    /// Minimized.
+    Hidden,
+    /// Only the titlebar is shown.
+    Shaded,
+    SkipTaskbar,
+    SkipPager,
+    DemandsAttention,
+}
+
+impl StateFlag {
+    pub const ALL: [StateFlag; 11] = [
+        StateFlag::Fullscreen,
+        StateFlag::MaximizedVert,
+        StateFlag::MaximizedHorz,
+        StateFlag::Above,
+        StateFlag::Below,
+        StateFlag::Sticky,
+        StateFlag::Hidden,
+        StateFlag::Shaded,
+        StateFlag::SkipTaskbar,
+        StateFlag::SkipPager,
+        StateFlag::DemandsAttention,
+    ];
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Action of a _NET_WM_STATE client message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateAction {
+    Remove,
+    Add,
+    Toggle,
+}
+
+impl StateAction {
+    pub fn from_value(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(StateAction::Remove),
+            1 => Some(StateAction::Add),
+            2 => Some(StateAction::Toggle),
+            _ => None,
+        }
+    }
+}
+
+/// Layers of the stacking order, from the bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StackingLayer {
+    Desktop,
+    Below,
+    Normal,
+    Above,
+    Dock,
+    Fullscreen,
+    /// Splash screens, notifications and popups mapped as toplevels.
+    Popup,
+}
+
+/// The set of states of a client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowState {
+    bits: u32,
+}
+
+impl WindowState {
+    pub fn from_flags(flags: impl IntoIterator<Item = StateFlag>) -> Self {
+        let mut state = WindowState::default();
+        for flag in flags {
+            state.set(flag, true);
+        }
+        state
+    }
+
+    pub fn contains(&self, flag: StateFlag) -> bool {
+        self.bits & flag.bit() != 0
+    }
+
+    pub fn set(&mut self, flag: StateFlag, enabled: bool) {
+        if enabled {
+            self.bits |= flag.bit();
+        } else {
+            self.bits &= !flag.bit();
+        }
+    }
+
+    pub fn with(mut self, flag: StateFlag, enabled: bool) -> Self {
+        self.set(flag, enabled);
+        self
+    }
+
+    pub fn apply(self, action: StateAction, flag: StateFlag) -> Self {
+        let enabled = match action {
+            StateAction::Remove => false,
+            StateAction::Add => true,
+            StateAction::Toggle => !self.contains(flag),
+        };
+        self.with(flag, enabled)
+    }
+
+    pub fn flags(&self) -> impl Iterator<Item = StateFlag> + '_ {
+        StateFlag::ALL
+            .into_iter()
+            .filter(|flag| self.contains(*flag))
+    }
+
+    /// Whether the geometry is decided by the window manager, not by the user.
+    pub fn has_fixed_geometry(&self) -> bool {
+        self.contains(StateFlag::Fullscreen)
+            || self.contains(StateFlag::MaximizedVert)
+            || self.contains(StateFlag::MaximizedHorz)
+    }
+
+    pub fn layer(&self) -> StackingLayer {
+        if self.contains(StateFlag::Fullscreen) {
+            StackingLayer::Fullscreen
+        } else if self.contains(StateFlag::Above) {
+            StackingLayer::Above
+        } else if self.contains(StateFlag::Below) {
+            StackingLayer::Below
+        } else {
+            StackingLayer::Normal
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_state() {
+        let state = WindowState::default()
+            .apply(StateAction::Add, StateFlag::MaximizedVert)
+            .apply(StateAction::Toggle, StateFlag::MaximizedHorz);
+        assert!(state.contains(StateFlag::MaximizedVert));
+        assert!(state.contains(StateFlag::MaximizedHorz));
+        assert!(state.has_fixed_geometry());
+        assert_eq!(
+            state.flags().collect::<Vec<_>>(),
+            vec![StateFlag::MaximizedVert, StateFlag::MaximizedHorz]
+        );
+
+        let state = state
+            .apply(StateAction::Toggle, StateFlag::MaximizedHorz)
+            .apply(StateAction::Remove, StateFlag::MaximizedVert)
+            .apply(StateAction::Remove, StateFlag::Sticky);
+        assert_eq!(state, WindowState::default());
+        assert!(!state.has_fixed_geometry());
+
+        assert_eq!(
+            WindowState::from_flags([StateFlag::Shaded, StateFlag::SkipPager]),
+            WindowState::default()
+                .with(StateFlag::SkipPager, true)
+                .with(StateFlag::Shaded, true)
+        );
+    }
+
+    #[test]
+    fn test_stacking_layer() {
+        assert_eq!(WindowState::default().layer(), StackingLayer::Normal);
+        assert_eq!(
+            WindowState::from_flags([StateFlag::Below]).layer(),
+            StackingLayer::Below
+        );
+        assert_eq!(
+            WindowState::from_flags([StateFlag::Above, StateFlag::Below]).layer(),
+            StackingLayer::Above
+        );
+        assert_eq!(
+            WindowState::from_flags([StateFlag::Above, StateFlag::Fullscreen]).layer(),
+            StackingLayer::Fullscreen
+        );
+        assert!(StackingLayer::Below < StackingLayer::Normal);
+        assert!(StackingLayer::Desktop < StackingLayer::Below);
+        assert!(StackingLayer::Dock < StackingLayer::Fullscreen);
+    }
+}
@@ -0,0 +1,162 @@
+use super::geometry::Geometry;
+
+/// Space reserved at the edges of the screen by a dock, from _NET_WM_STRUT_PARTIAL or _NET_WM_STRUT.
+/// The widths are measured from the edges of the screen, and each of them reserves the space
+/// only along its range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+    /// Ranges as `(start, end)`, both inclusive.
+    pub left_range: (u32, u32),
+    pub right_range: (u32, u32),
+    pub top_range: (u32, u32),
+    pub bottom_range: (u32, u32),
+}
+
+impl Strut {
+    /// Parses the 12 values of _NET_WM_STRUT_PARTIAL.
+    pub fn from_partial(values: &[u32]) -> Option<Self> {
+        if values.len() < 12 {
+            return None;
+        }
+        Some(Self {
+            left: values[0],
+            right: values[1],
+            top: values[2],
+            bottom: values[3],
+            left_range: (values[4], values[5]),
+            right_range: (values[6], values[7]),
+            top_range: (values[8], values[9]),
+            bottom_range: (values[10], values[11]),
+        })
+    }
+
+    /// Parses the 4 values of _NET_WM_STRUT, which reserve the whole edges.
+    pub fn from_legacy(values: &[u32]) -> Option<Self> {
+        if values.len() < 4 {
+            return None;
+        }
+        let whole_edge = (0, u32::MAX);
+        Some(Self {
+            left: values[0],
+            right: values[1],
+            top: values[2],
+            bottom: values[3],
+            left_range: whole_edge,
+            right_range: whole_edge,
+            top_range: whole_edge,
+            bottom_range: whole_edge,
+        })
+    }
+
+    /// Removes the reserved space from the area, which is a monitor or a part of it in the screen.
+    /// The struts whose ranges do not overlap the area leave it as it is.
+    pub fn reserve(&self, area: Geometry, screen: Geometry) -> Geometry {
+        let overlaps = |(start, end): (u32, u32), area_start: i32, area_length: u32| {
+            (start as i64) < area_start as i64 + area_length as i64
+                && (end as i64) >= area_start as i64
+        };
+
+        let mut left = area.x as i64;
+        let mut right = area.x as i64 + area.width as i64;
+        let mut top = area.y as i64;
+        let mut bottom = area.y as i64 + area.height as i64;
+        if self.left > 0 && overlaps(self.left_range, area.y, area.height) {
+            left = left.max(screen.x as i64 + self.left as i64);
+        }
+        if self.right > 0 && overlaps(self.right_range, area.y, area.height) {
+            right = right.min(screen.x as i64 + screen.width as i64 - self.right as i64);
+        }
+        if self.top > 0 && overlaps(self.top_range, area.x, area.width) {
+            top = top.max(screen.y as i64 + self.top as i64);
+        }
+        if self.bottom > 0 && overlaps(self.bottom_range, area.x, area.width) {
+            bottom = bottom.min(screen.y as i64 + screen.height as i64 - self.bottom as i64);
+        }
+
+        // struts covering the whole area are ignored, so the clients stay usable
+        if right <= left || bottom <= top {
+            return area;
+        }
+        Geometry {
+            x: left as i32,
+            y: top as i32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        }
+    }
+
+    /// The work area of the monitor, which is not reserved by any strut.
+    pub fn work_area<'a>(
+        monitor: Geometry,
+        screen: Geometry,
+        struts: impl IntoIterator<Item = &'a Strut>,
+    ) -> Geometry {
+        struts
+            .into_iter()
+            .fold(monitor, |area, strut| strut.reserve(area, screen))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Geometry = Geometry {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+
+    #[test]
+    fn test_parse_strut() {
+        assert_eq!(Strut::from_partial(&[0; 4]), None);
+        assert_eq!(Strut::from_legacy(&[]), None);
+
+        let strut = Strut::from_partial(&[0, 0, 30, 0, 0, 0, 0, 0, 0, 1919, 0, 0]).unwrap();
+        assert_eq!(strut.top, 30);
+        assert_eq!(strut.top_range, (0, 1919));
+
+        let strut = Strut::from_legacy(&[10, 0, 0, 20]).unwrap();
+        assert_eq!(strut.left, 10);
+        assert_eq!(strut.bottom, 20);
+        assert_eq!(strut.left_range, (0, u32::MAX));
+    }
+
+    #[test]
+    fn test_work_area() {
+        let top_panel = Strut::from_partial(&[0, 0, 30, 0, 0, 0, 0, 0, 0, 1919, 0, 0]).unwrap();
+        let bottom_panel = Strut::from_legacy(&[0, 0, 0, 40]).unwrap();
+        assert_eq!(
+            Strut::work_area(SCREEN, SCREEN, [&top_panel, &bottom_panel]),
+            Geometry {
+                x: 0,
+                y: 30,
+                width: 1920,
+                height: 1010
+            }
+        );
+        assert_eq!(Strut::work_area(SCREEN, SCREEN, []), SCREEN);
+
+        // a panel on the left half does not reserve the space of the right half
+        let left_half = Geometry {
+            width: 960,
+            ..SCREEN
+        };
+        let right_half = Geometry {
+            x: 960,
+            ..left_half
+        };
+        let half_panel = Strut::from_partial(&[0, 0, 30, 0, 0, 0, 0, 0, 0, 959, 0, 0]).unwrap();
+        assert_eq!(half_panel.reserve(left_half, SCREEN).y, 30);
+        assert_eq!(half_panel.reserve(right_half, SCREEN), right_half);
+
+        // a strut covering the whole screen is ignored
+        let huge = Strut::from_legacy(&[0, 0, 1080, 0]).unwrap();
+        assert_eq!(huge.reserve(SCREEN, SCREEN), SCREEN);
+    }
+}
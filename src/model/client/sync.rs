@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+/// How long to wait for a client to repaint before resizing it again anyway.
+pub const SYNC_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Progress of the _NET_WM_SYNC_REQUEST protocol of a client.
+/// The client sets its counter to the requested value when it has repainted for the new size.
+/// The wait is ended by a timer of the window manager after SYNC_TIMEOUT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncState {
+    last_value: u64,
+    // the value the client has been asked to set
+    waiting: Option<u64>,
+}
+
+impl SyncState {
+    /// Starts from the current value of the counter, which may have been used by another window manager.
+    pub fn new(counter_value: u64) -> Self {
+        Self {
+            last_value: counter_value,
+            waiting: None,
+        }
+    }
+
+    /// Starts waiting for the client, returning the value to send with the request.
+    pub fn request(&mut self) -> u64 {
+        self.last_value = self.last_value.wrapping_add(1);
+        self.waiting = Some(self.last_value);
+        self.last_value
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.waiting.is_some()
+    }
+
+    /// Handles the new value of the counter. Returns true if the client has caught up.
+    pub fn complete(&mut self, counter_value: u64) -> bool {
+        match self.waiting {
+            Some(value) if counter_value >= value => {
+                self.waiting = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Stops waiting for the client which has not repainted in time.
+    pub fn time_out(&mut self) {
+        self.waiting = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_state() {
+        let mut state = SyncState::new(10);
+        assert!(!state.is_waiting());
+
+        assert_eq!(state.request(), 11);
+        assert!(state.is_waiting());
+
+        // an older value does not finish the wait
+        assert!(!state.complete(10));
+        assert!(state.complete(11));
+        assert!(!state.is_waiting());
+        assert!(!state.complete(11));
+
+        assert_eq!(state.request(), 12);
+        state.time_out();
+        assert!(!state.is_waiting());
+        // the late update is ignored
+        assert!(!state.complete(12));
+    }
+}
@@ -0,0 +1,159 @@
+/// Encoding of a text property such as WM_NAME, given by the type of the property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// UTF8_STRING, used by _NET_WM_NAME.
+    Utf8,
+    /// STRING, which is ISO 8859-1 in ICCCM.
+    Latin1,
+    /// COMPOUND_TEXT, the ISO 2022 based encoding of the X Consortium.
+    CompoundText,
+}
+
+impl TextEncoding {
+    /// Decodes the text. Invalid bytes and unsupported character sets are replaced
+    /// with U+FFFD instead of failing, as a title is never worth an error.
+    pub fn decode(&self, value: &[u8]) -> String {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8_lossy(value).into_owned(),
+            TextEncoding::Latin1 => value.iter().map(|&byte| byte as char).collect(),
+            TextEncoding::CompoundText => decode_compound_text(value),
+        }
+    }
+}
+
+/// A character set designated to GL or GR in compound text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Charset {
+    Ascii,
+    /// The right half of ISO 8859-1.
+    Latin1,
+    /// A set which cannot be decoded, with the bytes per character.
+    Unknown(usize),
+}
+
+fn decode_compound_text(value: &[u8]) -> String {
+    const ESC: u8 = 0x1B;
+    const CSI: u8 = 0x9B;
+
+    let mut text = String::new();
+    // the initial state is ISO 8859-1
+    let mut gl = Charset::Ascii;
+    let mut gr = Charset::Latin1;
+    let mut i = 0;
+    while i < value.len() {
+        let byte = value[i];
+        if byte == ESC {
+            // ESC, intermediate bytes, and a final byte
+            let start = i + 1;
+            let mut end = start;
+            while end < value.len() && (0x20..=0x2F).contains(&value[end]) {
+                end += 1;
+            }
+            if end >= value.len() {
+                break;
+            }
+            let (intermediates, final_byte) = (&value[start..end], value[end]);
+            i = end + 1;
+            match (intermediates, final_byte) {
+                (b"(", b'B' | b'J') => gl = Charset::Ascii,
+                (b"(", _) => gl = Charset::Unknown(1),
+                (b")", _) => gr = Charset::Unknown(1),
+                (b"-", b'A') => gr = Charset::Latin1,
+                (b"-", _) => gr = Charset::Unknown(1),
+                (b"$" | b"$(", _) => gl = Charset::Unknown(2),
+                (b"$)", _) => gr = Charset::Unknown(2),
+                // UTF-8 until ESC % @, an extension of XFree86
+                (b"%", b'G') => {
+                    let length = value[i..]
+                        .windows(3)
+                        .position(|window| window == [ESC, b'%', b'@'])
+                        .unwrap_or(value.len() - i);
+                    text.push_str(&String::from_utf8_lossy(&value[i..i + length]));
+                    i = (i + length + 3).min(value.len());
+                }
+                // an extended segment of a named encoding, with its length in two bytes
+                (b"%/", _) => {
+                    if let [m, l, ..] = value[i..] {
+                        let length = ((m & 0x7F) as usize) * 128 + (l & 0x7F) as usize;
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        i = (i + 2 + length).min(value.len());
+                    } else {
+                        i = value.len();
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if byte == CSI {
+            // the direction of the text is ignored
+            i += 1;
+            while i < value.len() && !(0x40..=0x7E).contains(&value[i]) {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        let charset = match byte {
+            0x20 => {
+                text.push(' ');
+                i += 1;
+                continue;
+            }
+            0x21..=0x7E => gl,
+            0xA0..=0xFF => gr,
+            b'\n' | b'\t' => {
+                text.push(byte as char);
+                i += 1;
+                continue;
+            }
+            // the other control characters are not allowed
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        match charset {
+            Charset::Ascii | Charset::Latin1 => text.push(byte as char),
+            Charset::Unknown(width) => {
+                text.push(char::REPLACEMENT_CHARACTER);
+                i += width - 1;
+            }
+        }
+        i += 1;
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(TextEncoding::Utf8.decode("日本語".as_bytes()), "日本語");
+        assert_eq!(TextEncoding::Utf8.decode(b"caf\xe9"), "caf\u{FFFD}");
+        assert_eq!(TextEncoding::Latin1.decode(b"caf\xe9"), "café");
+    }
+
+    #[test]
+    fn test_decode_compound_text() {
+        let decode = |value: &[u8]| TextEncoding::CompoundText.decode(value);
+        // ISO 8859-1 without any escape sequence
+        assert_eq!(decode(b"caf\xe9\tok"), "café\tok");
+        // UTF-8 segment
+        assert_eq!(decode(b"a\x1b%G\xe6\x97\xa5\x1b%@b"), String::from("a日b"));
+        // JIS X 0208 cannot be decoded, but takes two bytes per character
+        assert_eq!(
+            decode(b"\x1b$(B\x46\x7c\x4b\x5c\x1b(Bjp"),
+            "\u{FFFD}\u{FFFD}jp"
+        );
+        // ISO 8859-2 in GR
+        assert_eq!(decode(b"\x1b-B\xb1z"), "\u{FFFD}z");
+        // an extended segment is skipped by its length
+        assert_eq!(decode(b"\x1b%/1\x80\x84abcdx"), "\u{FFFD}x");
+        // a truncated escape sequence ends the text
+        assert_eq!(decode(b"ab\x1b$"), "ab");
+    }
+}
@@ -0,0 +1,189 @@
+use crate::config::TitlebarButton;
+
+use super::Client;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonAppearance {
+    Normal,
+    Hovered,
+    Pressed,
+}
+
+/// The button shown in the place of the configured one.
+/// A client which is not responding cannot close itself, so its close button kills it instead.
+pub fn shown_button(button: TitlebarButton, responding: bool) -> TitlebarButton {
+    match button {
+        TitlebarButton::Close if !responding => TitlebarButton::ForceKill,
+        button => button,
+    }
+}
+
+/// Tracks the titlebar button under the cursor and the button being pressed.
+///
+/// A button is activated only if it is released over the same button that was pressed.
+#[derive(Debug, Clone, Copy)]
+pub struct TitlebarButtonState<WinId>
+where
+    WinId: Copy + Eq,
+{
+    hovered: Option<(Client<WinId>, TitlebarButton)>,
+    pressed: Option<(Client<WinId>, TitlebarButton)>,
+}
+
+impl<WinId> TitlebarButtonState<WinId>
+where
+    WinId: Copy + Eq,
+{
+    pub fn new() -> Self {
+        Self {
+            hovered: None,
+            pressed: None,
+        }
+    }
+
+    /// Updates the hovered button and returns the clients whose appearance has changed.
+    pub fn hover(&mut self, target: Option<(Client<WinId>, TitlebarButton)>) -> Vec<Client<WinId>> {
+        if self.hovered == target {
+            return Vec::new();
+        }
+        let changed = self
+            .hovered
+            .iter()
+            .chain(target.iter())
+            .map(|(client, _)| *client)
+            .collect();
+        self.hovered = target;
+        changed
+    }
+
+    pub fn press(&mut self, client: Client<WinId>, button: TitlebarButton) {
+        self.hovered = Some((client, button));
+        self.pressed = Some((client, button));
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed.is_some()
+    }
+
+    /// Releases the pressed button and returns it if the cursor is still on it.
+    pub fn release(
+        &mut self,
+        target: Option<(Client<WinId>, TitlebarButton)>,
+    ) -> Option<(Client<WinId>, TitlebarButton)> {
+        let pressed = self.pressed.take()?;
+        self.hovered = target;
+        if target == Some(pressed) {
+            Some(pressed)
+        } else {
+            None
+        }
+    }
+
+    /// The client whose button is pressed.
+    pub fn pressed_client(&self) -> Option<Client<WinId>> {
+        self.pressed.map(|(client, _)| client)
+    }
+
+    pub fn appearance(&self, client: Client<WinId>, button: TitlebarButton) -> ButtonAppearance {
+        let target = Some((client, button));
+        if self.hovered != target {
+            ButtonAppearance::Normal
+        } else if self.pressed == target {
+            ButtonAppearance::Pressed
+        } else {
+            ButtonAppearance::Hovered
+        }
+    }
+
+    pub fn forget(&mut self, client: Client<WinId>) {
+        if self.hovered.is_some_and(|(c, _)| c == client) {
+            self.hovered = None;
+        }
+        if self.pressed.is_some_and(|(c, _)| c == client) {
+            self.pressed = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shown_button() {
+        assert_eq!(
+            shown_button(TitlebarButton::Close, true),
+            TitlebarButton::Close
+        );
+        assert_eq!(
+            shown_button(TitlebarButton::Close, false),
+            TitlebarButton::ForceKill
+        );
+        assert_eq!(
+            shown_button(TitlebarButton::Maximize, false),
+            TitlebarButton::Maximize
+        );
+    }
+
+    #[test]
+    fn test_titlebar_button_state() {
+        let client = Client {
+            app_id: 1,
+            frame_id: 2,
+        };
+        let mut state = TitlebarButtonState::new();
+
+        assert_eq!(
+            state.hover(Some((client, TitlebarButton::Close))),
+            vec![client]
+        );
+        assert_eq!(state.hover(Some((client, TitlebarButton::Close))), vec![]);
+        assert_eq!(
+            state.appearance(client, TitlebarButton::Close),
+            ButtonAppearance::Hovered
+        );
+
+        // released on the same button
+        state.press(client, TitlebarButton::Close);
+        assert_eq!(
+            state.appearance(client, TitlebarButton::Close),
+            ButtonAppearance::Pressed
+        );
+        assert_eq!(
+            state.release(Some((client, TitlebarButton::Close))),
+            Some((client, TitlebarButton::Close))
+        );
+        assert!(!state.is_pressed());
+
+        // moved away while pressing
+        state.press(client, TitlebarButton::Close);
+        state.hover(Some((client, TitlebarButton::Maximize)));
+        assert_eq!(
+            state.appearance(client, TitlebarButton::Close),
+            ButtonAppearance::Normal
+        );
+        assert_eq!(
+            state.release(Some((client, TitlebarButton::Maximize))),
+            None
+        );
+        assert_eq!(
+            state.appearance(client, TitlebarButton::Maximize),
+            ButtonAppearance::Hovered
+        );
+
+        // pressed, left and came back
+        state.press(client, TitlebarButton::Minimize);
+        state.hover(None);
+        state.hover(Some((client, TitlebarButton::Minimize)));
+        assert_eq!(
+            state.release(Some((client, TitlebarButton::Minimize))),
+            Some((client, TitlebarButton::Minimize))
+        );
+
+        state.forget(client);
+        assert_eq!(
+            state.appearance(client, TitlebarButton::Minimize),
+            ButtonAppearance::Normal
+        );
+    }
+}
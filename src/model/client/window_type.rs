@@ -0,0 +1,163 @@
+use super::state::StackingLayer;
+
+/// The functional type of a window in _NET_WM_WINDOW_TYPE, defined in EWMH.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowType {
+    /// The desktop background, drawn below every other window.
+    Desktop,
+    /// Panels and docks.
+    Dock,
+    Toolbar,
+    Menu,
+    Utility,
+    Splash,
+    Dialog,
+    /// Menus and popups which are usually override-redirect, but may be mapped as toplevels.
+    DropdownMenu,
+    PopupMenu,
+    Tooltip,
+    Notification,
+    Combo,
+    Dnd,
+    #[default]
+    Normal,
+}
+
+impl WindowType {
+    pub const ALL: [WindowType; 14] = [
+        WindowType::Desktop,
+        WindowType::Dock,
+        WindowType::Toolbar,
+        WindowType::Menu,
+        WindowType::Utility,
+        WindowType::Splash,
+        WindowType::Dialog,
+        WindowType::DropdownMenu,
+        WindowType::PopupMenu,
+        WindowType::Tooltip,
+        WindowType::Notification,
+        WindowType::Combo,
+        WindowType::Dnd,
+        WindowType::Normal,
+    ];
+
+    /// Picks the first type the window manager knows from the types in the order of preference.
+    /// Windows without a known type are dialogs if they are transient for another window.
+    pub fn from_types(
+        types: impl IntoIterator<Item = Option<WindowType>>,
+        is_transient: bool,
+    ) -> Self {
+        types
+            .into_iter()
+            .flatten()
+            .next()
+            .unwrap_or(if is_transient {
+                WindowType::Dialog
+            } else {
+                WindowType::Normal
+            })
+    }
+
+    /// Whether the window gets the titlebar and the border.
+    pub fn is_decorated(&self) -> bool {
+        !matches!(
+            self,
+            WindowType::Desktop
+                | WindowType::Dock
+                | WindowType::Splash
+                | WindowType::DropdownMenu
+                | WindowType::PopupMenu
+                | WindowType::Tooltip
+                | WindowType::Notification
+                | WindowType::Combo
+                | WindowType::Dnd
+        )
+    }
+
+    /// Whether the window takes the input focus when it is clicked or activated.
+    pub fn is_focusable(&self) -> bool {
+        !matches!(
+            self,
+            WindowType::Dock
+                | WindowType::Splash
+                | WindowType::DropdownMenu
+                | WindowType::PopupMenu
+                | WindowType::Tooltip
+                | WindowType::Notification
+                | WindowType::Combo
+                | WindowType::Dnd
+        )
+    }
+
+    /// Whether the window is placed at the center of its parent when it is mapped.
+    pub fn is_centered_over_parent(&self) -> bool {
+        matches!(self, WindowType::Dialog | WindowType::Utility)
+    }
+
+    /// Whether the window is shown on every desktop unless it asks for a desktop.
+    pub fn is_on_all_desktops(&self) -> bool {
+        matches!(self, WindowType::Desktop | WindowType::Dock)
+    }
+
+    /// The layer the window is pinned to regardless of its _NET_WM_STATE.
+    pub fn layer(&self) -> Option<StackingLayer> {
+        match self {
+            WindowType::Desktop => Some(StackingLayer::Desktop),
+            WindowType::Dock => Some(StackingLayer::Dock),
+            WindowType::Splash
+            | WindowType::DropdownMenu
+            | WindowType::PopupMenu
+            | WindowType::Tooltip
+            | WindowType::Notification
+            | WindowType::Combo
+            | WindowType::Dnd => Some(StackingLayer::Popup),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_types() {
+        assert_eq!(WindowType::from_types([], false), WindowType::Normal);
+        assert_eq!(WindowType::from_types([], true), WindowType::Dialog);
+        // unknown types are skipped
+        assert_eq!(
+            WindowType::from_types(
+                [None, Some(WindowType::Dock), Some(WindowType::Normal)],
+                false
+            ),
+            WindowType::Dock
+        );
+        assert_eq!(
+            WindowType::from_types([Some(WindowType::Normal)], true),
+            WindowType::Normal
+        );
+    }
+
+    #[test]
+    fn test_policy() {
+        assert!(WindowType::Normal.is_decorated());
+        assert!(WindowType::Normal.is_focusable());
+        assert_eq!(WindowType::Normal.layer(), None);
+
+        assert!(!WindowType::Dock.is_decorated());
+        assert_eq!(WindowType::Dock.layer(), Some(StackingLayer::Dock));
+        assert!(!WindowType::Desktop.is_decorated());
+        assert_eq!(WindowType::Desktop.layer(), Some(StackingLayer::Desktop));
+        assert!(WindowType::Dock.is_on_all_desktops());
+        assert!(!WindowType::Normal.is_on_all_desktops());
+
+        assert!(!WindowType::Notification.is_decorated());
+        assert!(!WindowType::Notification.is_focusable());
+        assert!(!WindowType::Splash.is_focusable());
+
+        assert!(WindowType::Dialog.is_decorated());
+        assert!(WindowType::Dialog.is_centered_over_parent());
+        assert!(WindowType::Utility.is_centered_over_parent());
+        assert!(!WindowType::Normal.is_centered_over_parent());
+    }
+}
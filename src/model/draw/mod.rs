@@ -1,12 +1,26 @@
 mod utils;
 
-use crate::config::FrameConfig;
+use crate::config::{Color, TitlebarButton, WindowManagerConfig};
 
 use super::client::{
     geometry::{ClientGeometry, Geometry},
     hints::ClientHints,
+    titlebar::{shown_button, ButtonAppearance},
 };
 
+/// State of the client which selects the style of the frame in the theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameState {
+    Active,
+    Inactive,
+    Urgent,
+    /// The client has not answered _NET_WM_PING.
+    NotResponding,
+}
+
+/// Appended to the title of the clients which have not answered _NET_WM_PING.
+const NOT_RESPONDING_SUFFIX: &str = " (not responding)";
+
 pub struct FrameDrawContext {
     pub context: cairo::Context,
 }
@@ -16,35 +30,70 @@ impl FrameDrawContext {
         Self { context }
     }
 
-    pub fn draw(
+    fn set_source_color(&self, color: Color) {
+        self.context
+            .set_source_rgba(color.r, color.g, color.b, color.a);
+    }
+
+    pub fn draw<WinId>(
         &self,
         geometry: &ClientGeometry,
-        frame_config: &FrameConfig,
-        hints: &ClientHints,
+        config: &WindowManagerConfig,
+        state: FrameState,
+        hints: &ClientHints<WinId>,
+        button_appearance: impl Fn(TitlebarButton) -> ButtonAppearance,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let frame_config = &config.frame_config;
+        let theme = &config.theme;
+        let style = match state {
+            FrameState::Active => &theme.active,
+            FrameState::Inactive => &theme.inactive,
+            FrameState::Urgent => &theme.urgent,
+            FrameState::NotResponding => &theme.not_responding,
+        };
+
         self.context.set_operator(cairo::Operator::Source);
+
+        // clear the previous drawing, as the shadow may not cover the same area
+        self.set_source_color(Color::TRANSPARENT);
+        self.context.paint()?;
+
         let outer_frame_draw_area = geometry.parse_as_outer_frame_draw_area();
         let inner_frame_draw_area = geometry.parse_as_inner_frame_draw_area();
 
-        let drop_shadow_shrink_ratio = 0.7;
-        let drop_shadow_shrink_amount = frame_config.border_width as f64 * drop_shadow_shrink_ratio;
-        let shrink_outer_geom = Geometry {
-            x: outer_frame_draw_area.x + drop_shadow_shrink_amount as i32,
-            y: outer_frame_draw_area.y + drop_shadow_shrink_amount as i32,
-            width: outer_frame_draw_area.width - drop_shadow_shrink_amount as u32,
-            height: outer_frame_draw_area.height - drop_shadow_shrink_amount as u32,
-        };
+        let shadow_size = style.shadow_size.min(frame_config.border_width as f64);
+        if shadow_size > 0.0 {
+            let drop_shadow_shrink_amount = (frame_config.border_width as f64 - shadow_size) as i32;
+            let (offset_x, offset_y) = (style.shadow_offset.0 as i32, style.shadow_offset.1 as i32);
+            let shrink_outer_geom = Geometry {
+                x: outer_frame_draw_area.x + drop_shadow_shrink_amount + offset_x,
+                y: outer_frame_draw_area.y + drop_shadow_shrink_amount + offset_y,
+                width: outer_frame_draw_area.width - drop_shadow_shrink_amount as u32,
+                height: outer_frame_draw_area.height - drop_shadow_shrink_amount as u32,
+            };
+            let shadow_inner_geom = Geometry {
+                x: inner_frame_draw_area.x + offset_x,
+                y: inner_frame_draw_area.y + offset_y,
+                ..inner_frame_draw_area
+            };
 
-        utils::drop_shadow(
-            &self.context,
-            &shrink_outer_geom,
-            &inner_frame_draw_area,
-            (0.0, 0.0, 0.0, 0.0),
-            (0.0, 0.0, 0.0, 0.3),
-        )?;
+            let shadow_color = style.shadow_color;
+            utils::drop_shadow(
+                &self.context,
+                &shrink_outer_geom,
+                &shadow_inner_geom,
+                (shadow_color.r, shadow_color.g, shadow_color.b, 0.0),
+                (
+                    shadow_color.r,
+                    shadow_color.g,
+                    shadow_color.b,
+                    shadow_color.a,
+                ),
+            )?;
+        }
 
         // draw inner frame
-        self.context.set_source_rgba(0.5, 0.4, 0.3, 1.0);
+        self.set_source_color(style.frame_color);
         self.context.rectangle(
             inner_frame_draw_area.x as f64,
             inner_frame_draw_area.y as f64,
@@ -67,21 +116,81 @@ impl FrameDrawContext {
 
         self.context.fill()?;
 
-        let title_margin = frame_config.titlebar_height as f64 * 0.2;
-        let title_font_size = frame_config.titlebar_height as f64 * 0.6;
+        // draw border line around the inner frame
+        if style.border_line_width > 0.0 {
+            let half_line_width = style.border_line_width / 2.0;
+            self.set_source_color(style.border_color);
+            self.context.set_line_width(style.border_line_width);
+            self.context.rectangle(
+                inner_frame_draw_area.x as f64 + half_line_width,
+                inner_frame_draw_area.y as f64 + half_line_width,
+                inner_frame_draw_area.width as f64 - style.border_line_width,
+                inner_frame_draw_area.height as f64 - style.border_line_width,
+            );
+            self.context.stroke()?;
+        }
+
+        // draw titlebar buttons
+        for (button, area) in geometry.parse_as_button_draw_areas(&config.titlebar_config) {
+            let button = shown_button(button, state != FrameState::NotResponding);
+            let background = match button_appearance(button) {
+                ButtonAppearance::Normal => style.button_color,
+                ButtonAppearance::Hovered => style.button_hover_color,
+                ButtonAppearance::Pressed => style.button_pressed_color,
+            };
+            utils::titlebar_button(
+                &self.context,
+                button,
+                &area,
+                (background.r, background.g, background.b, background.a),
+                (
+                    style.title_color.r,
+                    style.title_color.g,
+                    style.title_color.b,
+                    style.title_color.a,
+                ),
+            )?;
+        }
+
+        let title_draw_area = geometry.parse_as_title_draw_area(&config.titlebar_config);
+        let title_margin = frame_config.titlebar_height as f64 * theme.title_margin_ratio;
+        let title_font_size = frame_config.titlebar_height as f64 * theme.title_font_ratio;
+
+        // keep the title away from the buttons
+        self.context.save()?;
+        self.context.rectangle(
+            title_draw_area.x as f64,
+            title_draw_area.y as f64,
+            title_draw_area.width as f64,
+            title_draw_area.height as f64,
+        );
+        self.context.clip();
 
         // draw title in hints
-        self.context.set_source_rgba(0.95, 0.95, 0.95, 1.0);
+        self.set_source_color(style.title_color);
         self.context.move_to(
-            inner_frame_draw_area.x as f64 + title_margin,
-            inner_frame_draw_area.y as f64 + frame_config.titlebar_height as f64 - title_margin,
+            title_draw_area.x as f64 + title_margin,
+            title_draw_area.y as f64 + frame_config.titlebar_height as f64 - title_margin,
         );
         // font size
         self.context.set_font_size(title_font_size);
         // font family
-        self.context
-            .select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
-        self.context.show_text(&hints.title)?;
+        self.context.select_font_face(
+            &theme.title_font,
+            cairo::FontSlant::Normal,
+            if style.title_bold {
+                cairo::FontWeight::Bold
+            } else {
+                cairo::FontWeight::Normal
+            },
+        );
+        if state == FrameState::NotResponding {
+            self.context
+                .show_text(&format!("{}{}", hints.title, NOT_RESPONDING_SUFFIX))?;
+        } else {
+            self.context.show_text(&hints.title)?;
+        }
+        self.context.restore()?;
 
         Ok(())
     }
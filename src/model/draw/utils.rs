@@ -1,6 +1,9 @@
 use cairo::{LinearGradient, RadialGradient};
 
-use crate::model::client::geometry::{self, Geometry};
+use crate::{
+    config::TitlebarButton,
+    model::client::geometry::{self, Geometry},
+};
 
 enum Alignment {
     Top,
@@ -267,3 +270,76 @@ pub fn drop_shadow(
 
     Ok(())
 }
+
+pub fn titlebar_button(
+    context: &cairo::Context,
+    button: TitlebarButton,
+    area: &Geometry,
+    color_background: (f64, f64, f64, f64),
+    color_symbol: (f64, f64, f64, f64),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let center = (
+        area.x as f64 + area.width as f64 / 2.0,
+        area.y as f64 + area.height as f64 / 2.0,
+    );
+    let size = area.width.min(area.height) as f64;
+
+    // background
+    context.set_source_rgba(
+        color_background.0,
+        color_background.1,
+        color_background.2,
+        color_background.3,
+    );
+    context.arc(
+        center.0,
+        center.1,
+        size * 0.4,
+        0.0,
+        2.0 * std::f64::consts::PI,
+    );
+    context.fill()?;
+
+    // symbol
+    let half = size * 0.15;
+    context.set_source_rgba(
+        color_symbol.0,
+        color_symbol.1,
+        color_symbol.2,
+        color_symbol.3,
+    );
+    context.set_line_width((size * 0.08).max(1.0));
+    match button {
+        TitlebarButton::Close => {
+            context.move_to(center.0 - half, center.1 - half);
+            context.line_to(center.0 + half, center.1 + half);
+            context.move_to(center.0 + half, center.1 - half);
+            context.line_to(center.0 - half, center.1 + half);
+        }
+        TitlebarButton::Maximize => {
+            context.rectangle(center.0 - half, center.1 - half, 2.0 * half, 2.0 * half);
+        }
+        TitlebarButton::Minimize => {
+            context.move_to(center.0 - half, center.1 + half);
+            context.line_to(center.0 + half, center.1 + half);
+        }
+        // the cross of the close button in a ring
+        TitlebarButton::ForceKill => {
+            context.move_to(center.0 - half, center.1 - half);
+            context.line_to(center.0 + half, center.1 + half);
+            context.move_to(center.0 + half, center.1 - half);
+            context.line_to(center.0 - half, center.1 + half);
+            context.new_sub_path();
+            context.arc(
+                center.0,
+                center.1,
+                size * 0.3,
+                0.0,
+                2.0 * std::f64::consts::PI,
+            );
+        }
+    }
+    context.stroke()?;
+
+    Ok(())
+}
@@ -1,2 +1,5 @@
 pub mod client;
 pub mod draw;
+pub mod monitor;
+pub mod scheduler;
+pub mod workspace;
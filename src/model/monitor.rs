@@ -0,0 +1,165 @@
+use super::client::{geometry::Geometry, strut::Strut};
+
+/// The monitors of the screen, each with its work area which is not reserved by the struts.
+/// The clients are placed and maximized in the work area of the monitor they are on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitors {
+    screen: Geometry,
+    areas: Vec<Geometry>,
+    work_areas: Vec<Geometry>,
+}
+
+impl Monitors {
+    /// Creates the monitors in the screen. Without any monitor, the whole screen is the only one.
+    /// The work areas are the whole monitors until update_work_areas is called.
+    pub fn new(screen: Geometry, mut areas: Vec<Geometry>) -> Self {
+        if areas.is_empty() {
+            areas.push(screen);
+        }
+        Self {
+            screen,
+            work_areas: areas.clone(),
+            areas,
+        }
+    }
+
+    /// Recomputes the work area of each monitor from the struts, which are relative to the screen.
+    pub fn update_work_areas<'a>(&mut self, struts: impl IntoIterator<Item = &'a Strut>) {
+        let struts = struts.into_iter().collect::<Vec<_>>();
+        self.work_areas = self
+            .areas
+            .iter()
+            .map(|area| Strut::work_area(*area, self.screen, struts.iter().copied()))
+            .collect();
+    }
+
+    /// The monitor containing the center of the area,
+    /// or the one overlapping it the most if the center is off every monitor.
+    fn index_for(&self, area: Geometry) -> usize {
+        let center_x = area.x + area.width as i32 / 2;
+        let center_y = area.y + area.height as i32 / 2;
+        self.areas
+            .iter()
+            .position(|monitor| monitor.contains(center_x, center_y))
+            .unwrap_or_else(|| {
+                // reversed to take the first of the ties, which is the first monitor if none overlaps
+                (0..self.areas.len())
+                    .rev()
+                    .max_by_key(|index| overlap(self.areas[*index], area))
+                    .unwrap_or(0)
+            })
+    }
+
+    /// The whole monitor the area is on, which a fullscreen client covers.
+    pub fn area_for(&self, area: Geometry) -> Geometry {
+        self.areas[self.index_for(area)]
+    }
+
+    /// The work area of the monitor the area is on.
+    pub fn work_area_for(&self, area: Geometry) -> Geometry {
+        self.work_areas[self.index_for(area)]
+    }
+
+    /// The bounding box of the work areas, published as _NET_WORKAREA,
+    /// which has a single rectangle for the whole screen.
+    pub fn bounding_work_area(&self) -> Geometry {
+        let left = self.work_areas.iter().map(|area| area.x).min();
+        let top = self.work_areas.iter().map(|area| area.y).min();
+        let right = self
+            .work_areas
+            .iter()
+            .map(|area| area.x + area.width as i32)
+            .max();
+        let bottom = self
+            .work_areas
+            .iter()
+            .map(|area| area.y + area.height as i32)
+            .max();
+        match (left, top, right, bottom) {
+            (Some(left), Some(top), Some(right), Some(bottom)) => Geometry {
+                x: left,
+                y: top,
+                width: (right - left) as u32,
+                height: (bottom - top) as u32,
+            },
+            _ => self.screen,
+        }
+    }
+}
+
+/// The size of the intersection of the areas.
+fn overlap(a: Geometry, b: Geometry) -> u64 {
+    let width = (a.x + a.width as i32).min(b.x + b.width as i32) - a.x.max(b.x);
+    let height = (a.y + a.height as i32).min(b.y + b.height as i32) - a.y.max(b.y);
+    width.max(0) as u64 * height.max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Geometry = Geometry {
+        x: 0,
+        y: 0,
+        width: 3840,
+        height: 1080,
+    };
+    const LEFT: Geometry = Geometry {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+    const RIGHT: Geometry = Geometry { x: 1920, ..LEFT };
+
+    #[test]
+    fn test_monitor_for_area() {
+        let monitors = Monitors::new(SCREEN, vec![LEFT, RIGHT]);
+        let window = |x| Geometry {
+            x,
+            y: 100,
+            width: 800,
+            height: 600,
+        };
+        assert_eq!(monitors.area_for(window(100)), LEFT);
+        assert_eq!(monitors.area_for(window(1800)), RIGHT);
+        // mostly off the screen on the right
+        assert_eq!(monitors.area_for(window(3500)), RIGHT);
+        // nowhere near any monitor
+        assert_eq!(monitors.area_for(window(-5000)), LEFT);
+
+        // the whole screen without any monitor
+        let monitors = Monitors::new(SCREEN, Vec::new());
+        assert_eq!(monitors.area_for(window(1800)), SCREEN);
+    }
+
+    #[test]
+    fn test_work_area_per_monitor() {
+        let mut monitors = Monitors::new(SCREEN, vec![LEFT, RIGHT]);
+        // a panel at the top of the left monitor only
+        let panel = Strut::from_partial(&[0, 0, 30, 0, 0, 0, 0, 0, 0, 1919, 0, 0]).unwrap();
+        monitors.update_work_areas([&panel]);
+        assert_eq!(
+            monitors.work_area_for(LEFT),
+            Geometry {
+                y: 30,
+                height: 1050,
+                ..LEFT
+            }
+        );
+        assert_eq!(monitors.work_area_for(RIGHT), RIGHT);
+        assert_eq!(monitors.bounding_work_area(), SCREEN);
+
+        // a panel at the top of both monitors
+        let panel = Strut::from_legacy(&[0, 0, 30, 0]).unwrap();
+        monitors.update_work_areas([&panel]);
+        assert_eq!(
+            monitors.bounding_work_area(),
+            Geometry {
+                y: 30,
+                height: 1050,
+                ..SCREEN
+            }
+        );
+    }
+}
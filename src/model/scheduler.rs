@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+/// Identifies a timer registered in the Scheduler, to cancel it later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerId(u64);
+
+#[derive(Debug, Clone)]
+struct Timer<T> {
+    id: TimerId,
+    deadline: Instant,
+    // repeating timers are rescheduled by the interval when they expire
+    interval: Option<Duration>,
+    task: T,
+}
+
+/// One-shot and repeating timers, which hand out their tasks when they expire.
+/// The main loop sleeps until next_deadline, so the timers run without a thread.
+#[derive(Debug, Clone)]
+pub struct Scheduler<T> {
+    timers: Vec<Timer<T>>,
+    next_id: u64,
+}
+
+impl<T: Clone> Scheduler<T> {
+    pub fn new() -> Self {
+        Self {
+            timers: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Runs the task once at the deadline.
+    pub fn schedule_once(&mut self, deadline: Instant, task: T) -> TimerId {
+        self.push(deadline, None, task)
+    }
+
+    /// Runs the task at the first deadline and every interval after it.
+    pub fn schedule_repeating(&mut self, first: Instant, interval: Duration, task: T) -> TimerId {
+        // a zero interval would expire forever within a single call of expire
+        self.push(first, Some(interval.max(Duration::from_millis(1))), task)
+    }
+
+    fn push(&mut self, deadline: Instant, interval: Option<Duration>, task: T) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id,
+            deadline,
+            interval,
+            task,
+        });
+        id
+    }
+
+    /// Removes the timer. Returns false if it has already expired or been cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let count = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() != count
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers.iter().map(|timer| timer.deadline).min()
+    }
+
+    /// Takes the tasks of the expired timers in the order of their deadlines.
+    /// A repeating timer runs once even if it has missed several intervals.
+    pub fn expire(&mut self, now: Instant) -> Vec<T> {
+        let mut expired = self
+            .timers
+            .iter()
+            .filter(|timer| timer.deadline <= now)
+            .map(|timer| (timer.deadline, timer.id, timer.task.clone()))
+            .collect::<Vec<_>>();
+        expired.sort_by_key(|(deadline, TimerId(id), _)| (*deadline, *id));
+
+        self.timers.retain_mut(|timer| {
+            if timer.deadline > now {
+                return true;
+            }
+            match timer.interval {
+                Some(interval) => {
+                    while timer.deadline <= now {
+                        timer.deadline += interval;
+                    }
+                    true
+                }
+                None => false,
+            }
+        });
+        expired.into_iter().map(|(_, _, task)| task).collect()
+    }
+}
+
+impl<T: Clone> Default for Scheduler<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_once() {
+        let now = Instant::now();
+        let second = Duration::from_secs(1);
+        let mut scheduler = Scheduler::new();
+        assert_eq!(scheduler.next_deadline(), None);
+
+        scheduler.schedule_once(now + second * 2, "later");
+        let cancelled = scheduler.schedule_once(now + second * 3, "cancelled");
+        scheduler.schedule_once(now + second, "sooner");
+        assert_eq!(scheduler.next_deadline(), Some(now + second));
+
+        assert!(scheduler.cancel(cancelled));
+        assert!(!scheduler.cancel(cancelled));
+        assert!(scheduler.expire(now).is_empty());
+        assert_eq!(scheduler.expire(now + second * 5), vec!["sooner", "later"]);
+        assert_eq!(scheduler.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_schedule_repeating() {
+        let now = Instant::now();
+        let second = Duration::from_secs(1);
+        let mut scheduler = Scheduler::new();
+        let id = scheduler.schedule_repeating(now + second, second * 2, "tick");
+
+        assert_eq!(scheduler.expire(now + second), vec!["tick"]);
+        assert_eq!(scheduler.next_deadline(), Some(now + second * 3));
+
+        // the missed runs are skipped
+        assert_eq!(scheduler.expire(now + second * 8), vec!["tick"]);
+        assert_eq!(scheduler.next_deadline(), Some(now + second * 9));
+
+        assert!(scheduler.cancel(id));
+        assert_eq!(scheduler.next_deadline(), None);
+    }
+}
@@ -0,0 +1,195 @@
+/// The desktop a client belongs to, in _NET_WM_DESKTOP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Desktop {
+    Index(u32),
+    /// Shown on every desktop.
+    All,
+}
+
+impl Desktop {
+    const ALL_VALUE: u32 = 0xFFFFFFFF;
+
+    pub fn from_value(value: u32) -> Self {
+        if value == Desktop::ALL_VALUE {
+            Desktop::All
+        } else {
+            Desktop::Index(value)
+        }
+    }
+
+    pub fn to_value(self) -> u32 {
+        match self {
+            Desktop::Index(index) => index,
+            Desktop::All => Desktop::ALL_VALUE,
+        }
+    }
+}
+
+/// The virtual desktops and the one currently shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspaces {
+    names: Vec<String>,
+    current: u32,
+}
+
+impl Workspaces {
+    /// Creates the desktops with the names. At least one desktop is created.
+    pub fn new(names: Vec<String>) -> Self {
+        let mut workspaces = Self {
+            names: Vec::new(),
+            current: 0,
+        };
+        workspaces.set_names(names);
+        workspaces
+    }
+
+    pub fn count(&self) -> u32 {
+        self.names.len() as u32
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Switches the current desktop. Returns false if the desktop does not exist.
+    pub fn switch_to(&mut self, index: u32) -> bool {
+        if index >= self.count() {
+            return false;
+        }
+        self.current = index;
+        true
+    }
+
+    /// Changes the number of desktops. The added desktops are named by their numbers.
+    pub fn set_count(&mut self, count: u32) {
+        let count = count.max(1) as usize;
+        let mut names = self.names.clone();
+        names.truncate(count);
+        while names.len() < count {
+            names.push((names.len() + 1).to_string());
+        }
+        self.set_names(names);
+    }
+
+    /// Replaces the desktops by the named ones, keeping the current desktop if it still exists.
+    pub fn set_names(&mut self, mut names: Vec<String>) {
+        if names.is_empty() {
+            names.push(String::from("1"));
+        }
+        self.names = names;
+        self.current = self.current.min(self.count() - 1);
+    }
+
+    pub fn is_visible(&self, desktop: Desktop) -> bool {
+        match desktop {
+            Desktop::Index(index) => index == self.current,
+            Desktop::All => true,
+        }
+    }
+
+    /// The desktop of a client after the desktops have changed.
+    /// Clients on the removed desktops are moved to the last one.
+    pub fn clamp(&self, desktop: Desktop) -> Desktop {
+        match desktop {
+            Desktop::Index(index) if index >= self.count() => Desktop::Index(self.count() - 1),
+            desktop => desktop,
+        }
+    }
+
+    /// The desktop of a client after its _NET_WM_STATE_STICKY has changed.
+    /// A sticky client is on every desktop, and is left on the current one when it is no longer sticky.
+    pub fn sticky_desktop(&self, desktop: Desktop, sticky: bool) -> Desktop {
+        match (desktop, sticky) {
+            (_, true) => Desktop::All,
+            (Desktop::All, false) => Desktop::Index(self.current),
+            (desktop, false) => desktop,
+        }
+    }
+
+    /// Whether a client may be moved to the desktop.
+    pub fn contains(&self, desktop: Desktop) -> bool {
+        self.clamp(desktop) == desktop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_desktop_value() {
+        assert_eq!(Desktop::from_value(2), Desktop::Index(2));
+        assert_eq!(Desktop::from_value(0xFFFFFFFF), Desktop::All);
+        assert_eq!(Desktop::All.to_value(), 0xFFFFFFFF);
+        assert_eq!(Desktop::Index(3).to_value(), 3);
+    }
+
+    #[test]
+    fn test_switch() {
+        let mut workspaces = Workspaces::new(names(&["web", "code", "chat"]));
+        assert_eq!(workspaces.count(), 3);
+        assert_eq!(workspaces.current(), 0);
+        assert!(workspaces.is_visible(Desktop::Index(0)));
+        assert!(!workspaces.is_visible(Desktop::Index(1)));
+
+        assert!(workspaces.switch_to(2));
+        assert!(workspaces.is_visible(Desktop::Index(2)));
+        assert!(workspaces.is_visible(Desktop::All));
+        assert!(!workspaces.switch_to(3));
+        assert_eq!(workspaces.current(), 2);
+
+        assert!(workspaces.contains(Desktop::Index(2)));
+        assert!(!workspaces.contains(Desktop::Index(3)));
+        assert!(workspaces.contains(Desktop::All));
+    }
+
+    #[test]
+    fn test_sticky_desktop() {
+        let mut workspaces = Workspaces::new(names(&["web", "code", "chat"]));
+        workspaces.switch_to(1);
+        assert_eq!(
+            workspaces.sticky_desktop(Desktop::Index(2), true),
+            Desktop::All
+        );
+        assert_eq!(workspaces.sticky_desktop(Desktop::All, true), Desktop::All);
+        assert_eq!(
+            workspaces.sticky_desktop(Desktop::All, false),
+            Desktop::Index(1)
+        );
+        assert_eq!(
+            workspaces.sticky_desktop(Desktop::Index(2), false),
+            Desktop::Index(2)
+        );
+    }
+
+    #[test]
+    fn test_change_count() {
+        let mut workspaces = Workspaces::new(names(&["web", "code", "chat"]));
+        workspaces.switch_to(2);
+
+        // the current desktop is removed
+        workspaces.set_count(2);
+        assert_eq!(workspaces.names(), names(&["web", "code"]));
+        assert_eq!(workspaces.current(), 1);
+        assert_eq!(workspaces.clamp(Desktop::Index(2)), Desktop::Index(1));
+        assert_eq!(workspaces.clamp(Desktop::Index(0)), Desktop::Index(0));
+        assert_eq!(workspaces.clamp(Desktop::All), Desktop::All);
+
+        workspaces.set_count(4);
+        assert_eq!(workspaces.names(), names(&["web", "code", "3", "4"]));
+        assert_eq!(workspaces.current(), 1);
+
+        // at least one desktop is kept
+        workspaces.set_count(0);
+        assert_eq!(workspaces.count(), 1);
+        assert_eq!(Workspaces::new(Vec::new()).count(), 1);
+    }
+}
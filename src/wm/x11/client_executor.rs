@@ -1,202 +1,1832 @@
-use x11rb::protocol::xproto::{ConfigureWindowAux, ConnectionExt, Window};
+use std::time::{Duration, Instant};
 
-use crate::model::{
-    client::{
-        container::ClientContainer, geometry::ClientGeometry, hints::ClientHints, map::ClientMap,
-        Client,
+use log::{error, info};
+use rustix::{
+    process::{kill_process, Pid, Signal},
+    system::uname,
+};
+use x11rb::{
+    connection::Connection,
+    cookie::Cookie,
+    errors::{
+        ConnectionError::{self, ParseError},
+        ReplyError,
+    },
+    properties::{WmClass, WmHints as XWmHints, WmHintsState, WmSizeHints},
+    protocol::randr::{ConnectionExt as _, NotifyMask},
+    protocol::sync::{
+        Alarm, ChangeAlarmAux, ConnectionExt as _, Counter, CreateAlarmAux, Int64, TESTTYPE,
+        VALUETYPE,
     },
-    draw::FrameDrawContext,
+    protocol::xproto::{
+        Atom, AtomEnum, ClientMessageEvent, ConfigureNotifyEvent, ConfigureWindowAux,
+        ConnectionExt, EventMask, GetPropertyReply, Gravity as XGravity, InputFocus, MapState,
+        PropMode, SetMode, StackMode, Window, CONFIGURE_NOTIFY_EVENT,
+    },
+    wrapper::ConnectionExt as _,
+    xcb_ffi::XCBConnection,
+};
+
+use crate::{
+    config::{FrameConfig, TitlebarButton},
+    model::{
+        client::{
+            container::ClientContainer,
+            focus::{ActivationSource, FocusState},
+            geometry::{ClientGeometry, Geometry},
+            hints::{
+                ClientHints, Gravity, Icon, InitialState, Protocol, SizeHints, WindowClass, WmHints,
+            },
+            map::ClientMap,
+            ping::PingState,
+            restart::RestartState,
+            state::{StateAction, StateFlag, WindowState},
+            strut::Strut,
+            sync::{SyncState, SYNC_TIMEOUT},
+            text::TextEncoding,
+            titlebar::TitlebarButtonState,
+            window_type::WindowType,
+            Client,
+        },
+        draw::{FrameDrawContext, FrameState},
+        monitor::Monitors,
+        scheduler::{Scheduler, TimerId},
+        workspace::{Desktop, Workspaces},
+    },
+};
+
+use super::{
+    ewmh,
+    graphics::CairoSurface,
+    session::{AtomCollection, X11Session},
 };
 
-use super::{graphics::CairoSurface, session::X11Session};
+pub struct ClientExecutor<'a> {
+    session: &'a X11Session,
+    client_container: ClientContainer<Window>,
+    surface_container: ClientMap<Window, CairoSurface>,
+    draw_queue: ClientMap<Window, ()>,
+    move_resize_queue: ClientMap<Window, ClientGeometry>,
+    hints_cache: ClientMap<Window, ClientHints<Window>>,
+    // geometries applied to the windows, as only the window manager configures the reparented apps
+    geometries: ClientMap<Window, ClientGeometry>,
+    button_state: TitlebarButtonState<Window>,
+    focus_state: FocusState<Window>,
+    window_states: ClientMap<Window, WindowState>,
+    window_types: ClientMap<Window, WindowType>,
+    struts: ClientMap<Window, Strut>,
+    // the screen area not reserved by the struts, published as _NET_WORKAREA
+    // the monitors and their work areas, with the whole screen as a monitor until init_monitors
+    monitors: Monitors,
+    workspaces: Workspaces,
+    desktops: ClientMap<Window, Desktop>,
+    // geometries to restore when the clients leave the maximized or fullscreen state
+    restore_geometries: ClientMap<Window, ClientGeometry>,
+    // number of UnmapNotify events caused by the window manager itself
+    pending_unmaps: ClientMap<Window, u32>,
+    scheduler: Scheduler<TimerTask>,
+    // clients asked to close, with the timers to kill them
+    close_deadlines: ClientMap<Window, TimerId>,
+    sync_counters: ClientMap<Window, SyncCounter>,
+    // clients supporting _NET_WM_PING which have been pinged
+    pings: ClientMap<Window, PingState>,
+    // clients with an unanswered ping, with the timers to mark them as not responding
+    ping_timeouts: ClientMap<Window, TimerId>,
+    // the repeating timer to ping every client
+    ping_timer: Option<TimerId>,
+}
+
+/// Work done by the timers of the executor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimerTask {
+    PingClients,
+    PingTimeout(Client<Window>),
+    SyncTimeout(Client<Window>),
+    ForceKill(Client<Window>),
+}
+
+/// The counter of a client supporting _NET_WM_SYNC_REQUEST,
+/// and the alarm which notifies the window manager when the client has updated it.
+#[derive(Debug, Clone, Copy)]
+struct SyncCounter {
+    counter: Counter,
+    alarm: Alarm,
+    state: SyncState,
+    // the timer to stop waiting for the client
+    timeout: Option<TimerId>,
+}
+
+/// Values of the WM_STATE property defined in ICCCM 4.1.3.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WmState {
+    Normal = 1,
+    Iconic = 3,
+}
+
+pub enum ClientRaisedResult {
+    Raised,
+    NotChanged,
+}
+
+impl<'a> ClientExecutor<'a> {
+    pub fn new(session: &'a X11Session) -> Self {
+        let mut executor = Self {
+            session,
+            client_container: ClientContainer::new(),
+            surface_container: ClientMap::new(),
+            draw_queue: ClientMap::new(),
+            move_resize_queue: ClientMap::new(),
+            hints_cache: ClientMap::new(),
+            geometries: ClientMap::new(),
+            button_state: TitlebarButtonState::new(),
+            focus_state: FocusState::new(),
+            window_states: ClientMap::new(),
+            window_types: ClientMap::new(),
+            struts: ClientMap::new(),
+            monitors: Monitors::new(screen_area(session), Vec::new()),
+            workspaces: Workspaces::new(session.config().workspace_config.names.clone()),
+            desktops: ClientMap::new(),
+            restore_geometries: ClientMap::new(),
+            pending_unmaps: ClientMap::new(),
+            scheduler: Scheduler::new(),
+            close_deadlines: ClientMap::new(),
+            sync_counters: ClientMap::new(),
+            pings: ClientMap::new(),
+            ping_timeouts: ClientMap::new(),
+            ping_timer: None,
+        };
+        executor.schedule_pings();
+        executor
+    }
+
+    pub fn container(&self) -> &ClientContainer<Window> {
+        &self.client_container
+    }
+
+    /// Fetches every hint of the client being managed.
+    /// WM_TRANSIENT_FOR has already been read to decide the window type, and is not read again.
+    pub fn update_hints(
+        &mut self,
+        client: Client<Window>,
+        transient_for: Option<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let hints_cache = self.fetch_hints(client, transient_for)?;
+        self.hints_cache.insert(client, hints_cache);
+        Ok(())
+    }
+
+    /// Refetches only the hint in the changed property.
+    /// Returns false if the property is not one of the hints.
+    pub fn update_hint(
+        &mut self,
+        client: Client<Window>,
+        property: Atom,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let connection = self.session.connection();
+        let atoms = self.session.atoms();
+        let window = client.app_id;
+        if self.hints_cache.query(client).is_none() {
+            return Ok(false);
+        }
+
+        if property == atoms._NET_WM_NAME || property == atoms.WM_NAME {
+            let title = self.fetch_title(window)?;
+            self.update_cached_hints(client, |hints| hints.title = title);
+        } else if property == u32::from(AtomEnum::WM_NORMAL_HINTS) {
+            let size_hints = self.fetch_size_hints(window)?;
+            self.update_cached_hints(client, |hints| hints.size_hints = size_hints);
+        } else if property == u32::from(AtomEnum::WM_CLASS) {
+            let class = optional_property(WmClass::get(connection, window)?.reply())?
+                .map(|wm_class| convert_class(&wm_class));
+            self.update_cached_hints(client, |hints| hints.class = class);
+        } else if property == u32::from(AtomEnum::WM_HINTS) {
+            let wm_hints = optional_property(XWmHints::get(connection, window)?.reply())?
+                .map(|wm_hints| convert_wm_hints(&wm_hints))
+                .unwrap_or_default();
+            self.update_cached_hints(client, |hints| hints.wm_hints = wm_hints);
+        } else if property == u32::from(AtomEnum::WM_TRANSIENT_FOR) {
+            let transient_for = self.fetch_transient_for(window)?;
+            self.update_cached_hints(client, |hints| hints.transient_for = transient_for);
+        } else if property == atoms.WM_CLIENT_MACHINE {
+            let client_machine =
+                decode_text_property(&self.request_text(window, property)?.reply()?, atoms);
+            self.update_cached_hints(client, |hints| hints.client_machine = client_machine);
+        } else if property == atoms._NET_WM_PID {
+            let pid = first_value32(&self.request_cardinal(window, property, 1)?.reply()?);
+            self.update_cached_hints(client, |hints| hints.pid = pid);
+        } else if property == atoms.WM_WINDOW_ROLE {
+            let role = decode_text_property(&self.request_text(window, property)?.reply()?, atoms);
+            self.update_cached_hints(client, |hints| hints.role = role);
+        } else if property == atoms._NET_WM_ICON {
+            let icons = parse_icons(
+                &self
+                    .request_cardinal(window, property, MAX_ICON_LENGTH)?
+                    .reply()?,
+            );
+            self.update_cached_hints(client, |hints| hints.icons = icons);
+        } else if property == atoms.WM_PROTOCOLS {
+            let protocols = protocols_from(&self.request_atoms(window, property)?.reply()?, atoms);
+            self.update_cached_hints(client, |hints| hints.protocols = protocols);
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Changes the cached hints in place, as the icons are too large to copy for each property.
+    fn update_cached_hints(
+        &mut self,
+        client: Client<Window>,
+        update: impl FnOnce(&mut ClientHints<Window>),
+    ) {
+        if let Some(hints) = self.hints_cache.query_mut(client) {
+            update(hints);
+        }
+    }
+
+    fn fetch_hints(
+        &self,
+        client: Client<Window>,
+        transient_for: Option<Window>,
+    ) -> Result<ClientHints<Window>, Box<dyn std::error::Error>> {
+        let connection = self.session.connection();
+        let atoms = self.session.atoms();
+        let window = client.app_id;
+
+        // every request is sent before waiting for the first reply, to save the round trips
+        let net_wm_name = self.request_text(window, atoms._NET_WM_NAME)?;
+        let wm_name = self.request_text(window, atoms.WM_NAME)?;
+        let size_hints = WmSizeHints::get_normal_hints(connection, window)?;
+        let class = WmClass::get(connection, window)?;
+        let wm_hints = XWmHints::get(connection, window)?;
+        let client_machine = self.request_text(window, atoms.WM_CLIENT_MACHINE)?;
+        let pid = self.request_cardinal(window, atoms._NET_WM_PID, 1)?;
+        let role = self.request_text(window, atoms.WM_WINDOW_ROLE)?;
+        let icons = self.request_cardinal(window, atoms._NET_WM_ICON, MAX_ICON_LENGTH)?;
+        let protocols = self.request_atoms(window, atoms.WM_PROTOCOLS)?;
+
+        Ok(ClientHints {
+            title: title_from(&net_wm_name.reply()?, &wm_name.reply()?, atoms),
+            size_hints: optional_property(size_hints.reply())?
+                .map(|wm_size_hints| convert_size_hints(&wm_size_hints))
+                .unwrap_or_default(),
+            class: optional_property(class.reply())?.map(|wm_class| convert_class(&wm_class)),
+            wm_hints: optional_property(wm_hints.reply())?
+                .map(|wm_hints| convert_wm_hints(&wm_hints))
+                .unwrap_or_default(),
+            transient_for,
+            client_machine: decode_text_property(&client_machine.reply()?, atoms),
+            pid: first_value32(&pid.reply()?),
+            role: decode_text_property(&role.reply()?, atoms),
+            icons: parse_icons(&icons.reply()?),
+            protocols: protocols_from(&protocols.reply()?, atoms),
+        })
+    }
+
+    /// Requests a text property of any type, to decode it by the type.
+    fn request_text(
+        &self,
+        window: Window,
+        property: Atom,
+    ) -> Result<Cookie<'_, XCBConnection, GetPropertyReply>, ConnectionError> {
+        self.session
+            .connection()
+            .get_property(false, window, property, AtomEnum::ANY, 0, 1024)
+    }
+
+    fn request_atoms(
+        &self,
+        window: Window,
+        property: Atom,
+    ) -> Result<Cookie<'_, XCBConnection, GetPropertyReply>, ConnectionError> {
+        self.session
+            .connection()
+            .get_property(false, window, property, AtomEnum::ATOM, 0, 1024)
+    }
+
+    fn request_cardinal(
+        &self,
+        window: Window,
+        property: Atom,
+        length: u32,
+    ) -> Result<Cookie<'_, XCBConnection, GetPropertyReply>, ConnectionError> {
+        self.session.connection().get_property(
+            false,
+            window,
+            property,
+            AtomEnum::CARDINAL,
+            0,
+            length,
+        )
+    }
+
+    /// Reads _NET_WM_NAME, or WM_NAME in the encoding given by its type if it is not set.
+    fn fetch_title(&self, window: Window) -> Result<String, Box<dyn std::error::Error>> {
+        let atoms = self.session.atoms();
+        let net_wm_name = self.request_text(window, atoms._NET_WM_NAME)?;
+        let wm_name = self.request_text(window, atoms.WM_NAME)?;
+        Ok(title_from(&net_wm_name.reply()?, &wm_name.reply()?, atoms))
+    }
+
+    /// Reads WM_NORMAL_HINTS, which is also needed to place the window before it is managed.
+    pub fn fetch_size_hints(
+        &self,
+        window: Window,
+    ) -> Result<SizeHints, Box<dyn std::error::Error>> {
+        let reply = WmSizeHints::get_normal_hints(self.session.connection(), window)?.reply();
+        Ok(optional_property(reply)?
+            .map(|wm_size_hints| convert_size_hints(&wm_size_hints))
+            .unwrap_or_default())
+    }
+
+    /// Reads _NET_WM_WINDOW_TYPE of the window before it is managed.
+    /// A window without the type is a dialog if it is transient for another window.
+    pub fn fetch_window_type(
+        &self,
+        window: Window,
+        is_transient: bool,
+    ) -> Result<WindowType, Box<dyn std::error::Error>> {
+        let atoms = self.session.atoms();
+        let property = self
+            .session
+            .connection()
+            .get_property(
+                false,
+                window,
+                atoms._NET_WM_WINDOW_TYPE,
+                AtomEnum::ATOM,
+                0,
+                1024,
+            )?
+            .reply()?;
+        Ok(WindowType::from_types(
+            property
+                .value32()
+                .into_iter()
+                .flatten()
+                .map(|atom| ewmh::window_type(atoms, atom)),
+            is_transient,
+        ))
+    }
+
+    /// Reads WM_TRANSIENT_FOR, the window which the dialog belongs to.
+    pub fn fetch_transient_for(
+        &self,
+        window: Window,
+    ) -> Result<Option<Window>, Box<dyn std::error::Error>> {
+        let property = self
+            .session
+            .connection()
+            .get_property(
+                false,
+                window,
+                AtomEnum::WM_TRANSIENT_FOR,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )?
+            .reply()?;
+        Ok(transient_for_from(&property, window))
+    }
+
+    pub fn get_window_type(&self, client: Client<Window>) -> WindowType {
+        self.window_types.query(client).copied().unwrap_or_default()
+    }
+
+    /// The frame configuration for the windows of the type, which may be shown without decorations.
+    pub fn frame_config_for(&self, window_type: WindowType) -> FrameConfig {
+        let frame_config = self.session.config().frame_config;
+        if window_type.is_decorated() {
+            frame_config
+        } else {
+            frame_config.frameless()
+        }
+    }
+
+    pub fn client_frame_config(&self, client: Client<Window>) -> FrameConfig {
+        self.frame_config_for(self.get_window_type(client))
+    }
+
+    /// The app area of the parent to center a dialog over,
+    /// or the work area of the monitor the dialog is on if it has no managed parent.
+    pub fn parent_area(&self, transient_for: Option<Window>, area: Geometry) -> Geometry {
+        transient_for
+            .and_then(|parent| self.client_container.query_client_from_app(parent))
+            .map(|parent| self.get_client_geometry(parent).parse_as_app())
+            .unwrap_or_else(|| self.work_area_for(area))
+    }
+
+    /// The work area of the monitor the area is on.
+    pub fn work_area_for(&self, area: Geometry) -> Geometry {
+        self.monitors.work_area_for(area)
+    }
+
+    /// Starts following the monitors by RandR, and reads them.
+    pub fn init_monitors(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.session.randr_supported() {
+            self.session
+                .connection()
+                .randr_select_input(self.session.screen().root, NotifyMask::SCREEN_CHANGE)?;
+        }
+        self.update_monitors()
+    }
+
+    /// Reads the size of the screen and the monitors in it, which RandR has changed.
+    /// Without RandR 1.5, the whole screen is a monitor.
+    pub fn update_monitors(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let connection = self.session.connection();
+        let root = self.session.screen().root;
+        let root_geometry = connection.get_geometry(root)?.reply()?;
+        let screen = Geometry {
+            x: 0,
+            y: 0,
+            width: root_geometry.width as u32,
+            height: root_geometry.height as u32,
+        };
+        let areas = if self.session.randr_supported() {
+            connection
+                .randr_get_monitors(root, true)?
+                .reply()?
+                .monitors
+                .iter()
+                .map(|monitor| Geometry {
+                    x: monitor.x as i32,
+                    y: monitor.y as i32,
+                    width: monitor.width as u32,
+                    height: monitor.height as u32,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        info!("Monitors in {:?}: {:?}", screen, areas);
+        self.apply_monitors(Monitors::new(screen, areas))
+    }
+
+    /// Reads _NET_WM_STRUT_PARTIAL, or _NET_WM_STRUT if it is absent, and updates the work area.
+    pub fn update_strut(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let atoms = self.session.atoms();
+        let mut strut = None;
+        for (property, parse) in [
+            (
+                atoms._NET_WM_STRUT_PARTIAL,
+                Strut::from_partial as fn(&[u32]) -> Option<Strut>,
+            ),
+            (atoms._NET_WM_STRUT, Strut::from_legacy),
+        ] {
+            let property = self
+                .session
+                .connection()
+                .get_property(false, client.app_id, property, AtomEnum::CARDINAL, 0, 12)?
+                .reply()?;
+            strut = property
+                .value32()
+                .and_then(|values| parse(&values.collect::<Vec<_>>()));
+            if strut.is_some() {
+                break;
+            }
+        }
+
+        match strut {
+            Some(strut) => self.struts.insert(client, strut),
+            None => self.struts.remove(client),
+        }
+        self.update_work_area()
+    }
+
+    /// Recomputes the work areas from the struts,
+    /// and fits the maximized clients to them if they have changed.
+    fn update_work_area(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.apply_monitors(self.monitors.clone())
+    }
+
+    /// Replaces the monitors, computing their work areas from the struts.
+    /// The maximized and fullscreen clients are fitted to them if they have changed.
+    fn apply_monitors(&mut self, mut monitors: Monitors) -> Result<(), Box<dyn std::error::Error>> {
+        monitors.update_work_areas(self.struts.iter().map(|(_, strut)| strut));
+        if monitors == self.monitors {
+            return Ok(());
+        }
+        self.monitors = monitors;
+        ewmh::set_work_area(
+            self.session,
+            self.monitors.bounding_work_area(),
+            self.workspaces.count(),
+        )?;
+
+        let restore_geometries = self.restore_geometries.iter().copied().collect::<Vec<_>>();
+        for (client, normal_geometry) in restore_geometries {
+            let window_state = self.get_window_state(client);
+            self.queue_move_resize(
+                client,
+                self.state_geometry(client, window_state, normal_geometry),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn get_size_hints(&self, client: Client<Window>) -> SizeHints {
+        self.hints_cache
+            .query(client)
+            .map(|hints| hints.size_hints)
+            .unwrap_or_default()
+    }
+
+    pub fn add_client(
+        &mut self,
+        app_id: Window,
+        frame_id: Window,
+        client_geometry: ClientGeometry,
+        window_type: WindowType,
+    ) -> Result<Client<Window>, Box<dyn std::error::Error>> {
+        let frame_geometry = client_geometry.parse_as_frame();
+        let surface = self
+            .session
+            .cairo_session()
+            .create_cairo_surface_for_window(
+                self.session,
+                frame_id,
+                frame_geometry.width as i32,
+                frame_geometry.height as i32,
+            )?;
+
+        let client = self.client_container.add_client(app_id, frame_id);
+
+        self.surface_container.insert(client, surface);
+        self.geometries.insert(client, client_geometry);
+        self.window_types.insert(client, window_type);
+
+        // reparenting a viewable window unmaps it, which must not be taken as a withdrawal
+        let app_attributes = self
+            .session
+            .connection()
+            .get_window_attributes(app_id)?
+            .reply()?;
+        if app_attributes.map_state != MapState::UNMAPPED {
+            self.expect_unmap(client);
+        }
+
+        // keep the app alive on the root if the window manager dies
+        self.session
+            .connection()
+            .change_save_set(SetMode::INSERT, app_id)?;
+
+        let app_draw_area = client_geometry.parse_as_app_draw_area();
+        self.session.connection().configure_window(
+            app_id,
+            &ConfigureWindowAux::default()
+                .width(app_draw_area.width)
+                .height(app_draw_area.height)
+                .border_width(0),
+        )?;
+        self.session.connection().reparent_window(
+            app_id,
+            frame_id,
+            app_draw_area.x as i16,
+            app_draw_area.y as i16,
+        )?;
+        ewmh::set_frame_extents(self.session, app_id, client_geometry.frame_extents())?;
+        self.update_client_lists()?;
+
+        Ok(client)
+    }
+
+    /// Stops managing the client withdrawn by the app.
+    /// The app may be destroyed already, so the errors of these requests are ignored.
+    pub fn unmanage_client(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.unframe_client(client)?;
+        // kept on shutdown, so the next window manager puts the app on the same desktop
+        self.session
+            .connection()
+            .delete_property(client.app_id, self.session.atoms()._NET_WM_DESKTOP)?;
+        Ok(())
+    }
+
+    /// Reparents the app back to the root and destroys the frame.
+    fn unframe_client(&mut self, client: Client<Window>) -> Result<(), Box<dyn std::error::Error>> {
+        let gravity = self.get_size_hints(client).gravity;
+        let app_geometry = self
+            .get_client_geometry(client)
+            .parse_as_unframed_app(gravity);
+        // the surface is dropped before its window is destroyed
+        self.remove_client(client)?;
+
+        self.session.connection().reparent_window(
+            client.app_id,
+            self.session.screen().root,
+            app_geometry.x as i16,
+            app_geometry.y as i16,
+        )?;
+        self.session
+            .connection()
+            .change_save_set(SetMode::DELETE, client.app_id)?;
+        self.session
+            .connection()
+            .delete_property(client.app_id, self.session.atoms()._NET_FRAME_EXTENTS)?;
+        self.session.connection().destroy_window(client.frame_id)?;
+        Ok(())
+    }
+
+    /// Unmanages every client, mapping the minimized ones so they are not lost.
+    /// The clients are released from the bottom to keep the stacking order on the root.
+    pub fn release_clients(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let tree = self
+            .session
+            .connection()
+            .query_tree(self.session.screen().root)?
+            .reply()?;
+        let clients = tree
+            .children
+            .iter()
+            .filter_map(|window| self.client_container.query_client_from_frame(*window))
+            .collect::<Vec<_>>();
+        for client in clients {
+            let window_state = self.get_window_state(client);
+            if !self.is_shown(client) || window_state.contains(StateFlag::Shaded) {
+                self.session.connection().map_window(client.app_id)?;
+                self.set_wm_state(client, WmState::Normal)?;
+            }
+            self.unframe_client(client)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the state of each client on its app, and the current desktop on the root.
+    /// The desktops of the clients are kept in _NET_WM_DESKTOP.
+    pub fn save_restart_state(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.session.connection().change_property32(
+            PropMode::REPLACE,
+            self.session.screen().root,
+            self.session.atoms()._CLEARWM_RESTART_STATE,
+            AtomEnum::CARDINAL,
+            &[self.workspaces.current()],
+        )?;
+        let focused_client = self.focus_state.focused();
+        for client in self.client_container.iter() {
+            let restart_state = RestartState {
+                geometry: self.get_client_geometry(*client).parse_as_app(),
+                restore_geometry: self
+                    .restore_geometries
+                    .query(*client)
+                    .map(|restore_geometry| restore_geometry.parse_as_app()),
+                minimized: self.is_minimized(*client),
+                focused: focused_client == Some(*client),
+            };
+            let restart_state_atom = self.session.atoms()._CLEARWM_RESTART_STATE;
+            self.session.connection().change_property32(
+                PropMode::REPLACE,
+                client.app_id,
+                restart_state_atom,
+                AtomEnum::CARDINAL,
+                &restart_state.encode(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Reads and removes the state saved on the window before a restart.
+    pub fn take_restart_state(
+        &self,
+        window: Window,
+    ) -> Result<Option<RestartState>, Box<dyn std::error::Error>> {
+        let property = self
+            .session
+            .connection()
+            .get_property(
+                true,
+                window,
+                self.session.atoms()._CLEARWM_RESTART_STATE,
+                AtomEnum::CARDINAL,
+                0,
+                64,
+            )?
+            .reply()?;
+        Ok(property
+            .value32()
+            .and_then(|values| RestartState::decode(&values.collect::<Vec<_>>())))
+    }
+
+    pub fn restore_restart_state(
+        &mut self,
+        client: Client<Window>,
+        restart_state: &RestartState,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(restore_geometry) = restart_state.restore_geometry {
+            let frame_config = self.client_frame_config(client);
+            self.restore_geometries.insert(
+                client,
+                ClientGeometry::from_app(
+                    restore_geometry.x,
+                    restore_geometry.y,
+                    restore_geometry.width,
+                    restore_geometry.height,
+                    frame_config,
+                ),
+            );
+        }
+        if restart_state.minimized {
+            self.minimize_client(client)?;
+        }
+        Ok(())
+    }
+
+    fn remove_client(&mut self, client: Client<Window>) -> Result<(), Box<dyn std::error::Error>> {
+        self.unfocus_client(client)?;
+        self.client_container.remove_client(client);
+        self.surface_container.remove(client);
+        self.draw_queue.remove(client);
+        self.move_resize_queue.remove(client);
+        self.hints_cache.remove(client);
+        self.geometries.remove(client);
+        self.button_state.forget(client);
+        self.window_states.remove(client);
+        self.window_types.remove(client);
+        self.desktops.remove(client);
+        self.restore_geometries.remove(client);
+        self.pending_unmaps.remove(client);
+        if let Some(timer) = self.close_deadlines.query(client) {
+            self.scheduler.cancel(*timer);
+            self.close_deadlines.remove(client);
+        }
+        self.pings.remove(client);
+        if let Some(timer) = self.ping_timeouts.query(client) {
+            self.scheduler.cancel(*timer);
+            self.ping_timeouts.remove(client);
+        }
+        self.remove_sync_counter(client)?;
+        if self.struts.query(client).is_some() {
+            self.struts.remove(client);
+            self.update_work_area()?;
+        }
+        self.update_client_lists()
+    }
+
+    /// Publishes the managed clients to the EWMH client lists.
+    fn update_client_lists(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let apps = self
+            .client_container
+            .iter()
+            .map(|client| client.app_id)
+            .collect::<Vec<_>>();
+        ewmh::set_client_list(self.session, &apps)?;
+
+        let apps_stacking = self
+            .client_container
+            .iter_stacking()
+            .map(|client| client.app_id)
+            .collect::<Vec<_>>();
+        ewmh::set_client_list_stacking(self.session, &apps_stacking)?;
+        Ok(())
+    }
+
+    pub fn set_wm_state(
+        &self,
+        client: Client<Window>,
+        state: WmState,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let wm_state = self.session.atoms().WM_STATE;
+        self.session.connection().change_property32(
+            PropMode::REPLACE,
+            client.app_id,
+            wm_state,
+            wm_state,
+            &[state as u32, x11rb::NONE],
+        )?;
+        Ok(())
+    }
+
+    /// Marks that the window manager is going to unmap the app.
+    fn expect_unmap(&mut self, client: Client<Window>) {
+        let pending_unmaps = self.pending_unmaps.query(client).copied().unwrap_or(0);
+        self.pending_unmaps.insert(client, pending_unmaps + 1);
+    }
+
+    /// Consumes an UnmapNotify event caused by the window manager.
+    /// Returns false if the client unmapped the window by itself.
+    pub fn consume_pending_unmap(&mut self, client: Client<Window>) -> bool {
+        match self.pending_unmaps.query(client) {
+            Some(&count) if count > 0 => {
+                self.pending_unmaps.insert(client, count - 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get_window_state(&self, client: Client<Window>) -> WindowState {
+        self.window_states
+            .query(client)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Reads the _NET_WM_STATE set by the client before it is mapped, and applies it.
+    pub fn load_window_state(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let atoms = self.session.atoms();
+        let property = self
+            .session
+            .connection()
+            .get_property(
+                false,
+                client.app_id,
+                atoms._NET_WM_STATE,
+                AtomEnum::ATOM,
+                0,
+                1024,
+            )?
+            .reply()?;
+        let window_state = WindowState::from_flags(
+            property
+                .value32()
+                .into_iter()
+                .flatten()
+                .filter_map(|atom| ewmh::state_flag(atoms, atom)),
+        );
+        // a client put on every desktop by load_desktop is sticky without the state
+        let sticky =
+            window_state.contains(StateFlag::Sticky) || self.get_desktop(client) == Desktop::All;
+        self.set_window_state(client, window_state.with(StateFlag::Sticky, sticky))
+    }
+
+    /// Handles a _NET_WM_STATE request, which changes one or two states at once.
+    pub fn change_window_state(
+        &mut self,
+        client: Client<Window>,
+        action: StateAction,
+        flags: &[StateFlag],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let window_state = flags
+            .iter()
+            .fold(self.get_window_state(client), |window_state, flag| {
+                window_state.apply(action, *flag)
+            });
+        self.set_window_state(client, window_state)
+    }
+
+    /// Moves the client to the state, and reflects it to the _NET_WM_STATE property.
+    pub fn set_window_state(
+        &mut self,
+        client: Client<Window>,
+        window_state: WindowState,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let old_state = self.get_window_state(client);
+        let was_shown = self.is_shown(client);
+        self.window_states.insert(client, window_state);
+        let changed = |flag| old_state.contains(flag) != window_state.contains(flag);
+
+        // a sticky client is on every desktop, as in move_client_to_desktop
+        if changed(StateFlag::Sticky) {
+            let desktop = self.workspaces.sticky_desktop(
+                self.get_desktop(client),
+                window_state.contains(StateFlag::Sticky),
+            );
+            self.desktops.insert(client, desktop);
+            ewmh::set_wm_desktop(self.session, client.app_id, desktop)?;
+        }
+        self.update_visibility(client, was_shown, old_state)?;
+        // the app of the client shown or hidden above is already mapped as the state
+        if changed(StateFlag::Shaded) && was_shown && self.is_shown(client) {
+            // only the titlebar is left, so the app is unmapped
+            if window_state.contains(StateFlag::Shaded) {
+                self.expect_unmap(client);
+                self.session.connection().unmap_window(client.app_id)?;
+            } else {
+                self.session.connection().map_window(client.app_id)?;
+            }
+        }
+
+        if changed(StateFlag::Fullscreen)
+            || changed(StateFlag::MaximizedVert)
+            || changed(StateFlag::MaximizedHorz)
+        {
+            let normal_geometry = self
+                .restore_geometries
+                .query(client)
+                .copied()
+                .unwrap_or_else(|| self.get_client_geometry(client));
+            if window_state.has_fixed_geometry() {
+                self.restore_geometries.insert(client, normal_geometry);
+            } else {
+                self.restore_geometries.remove(client);
+            }
+            self.queue_move_resize(
+                client,
+                self.state_geometry(client, window_state, normal_geometry),
+            );
+        } else if changed(StateFlag::Shaded) {
+            self.queue_move_resize(client, self.get_client_geometry(client));
+        }
+
+        if old_state.layer() != window_state.layer() {
+            self.restack_clients()?;
+        }
+        if changed(StateFlag::DemandsAttention) {
+            self.queue_draw(client);
+        }
+        self.publish_window_state(client, window_state)
+    }
+
+    fn publish_window_state(
+        &self,
+        client: Client<Window>,
+        window_state: WindowState,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let atoms = self.session.atoms();
+        let state_atoms = window_state
+            .flags()
+            .map(|flag| ewmh::state_atom(atoms, flag))
+            .collect::<Vec<_>>();
+        ewmh::set_window_state(self.session, client.app_id, &state_atoms)
+    }
+
+    /// The geometry decided by the state, from the geometry in the normal state.
+    fn state_geometry(
+        &self,
+        client: Client<Window>,
+        window_state: WindowState,
+        normal_geometry: ClientGeometry,
+    ) -> ClientGeometry {
+        let frame_config = self.client_frame_config(client);
+        let normal_geometry = normal_geometry.with_frame_config(frame_config);
+        // the client stays on the monitor it is on in the normal state
+        let area = normal_geometry.parse_as_frame();
+        if window_state.contains(StateFlag::Fullscreen) {
+            ClientGeometry::fullscreen_in(self.monitors.area_for(area), frame_config)
+        } else {
+            normal_geometry.maximized_on_axes(
+                self.monitors.work_area_for(area),
+                window_state.contains(StateFlag::MaximizedHorz),
+                window_state.contains(StateFlag::MaximizedVert),
+            )
+        }
+    }
+
+    /// Whether the client is on the current desktop and not minimized.
+    fn is_shown(&self, client: Client<Window>) -> bool {
+        !self.get_window_state(client).contains(StateFlag::Hidden)
+            && self.workspaces.is_visible(self.get_desktop(client))
+    }
+
+    /// Maps or unmaps the client after its state or desktop has changed.
+    /// `old_state` is the state when the client was shown or hidden.
+    fn update_visibility(
+        &mut self,
+        client: Client<Window>,
+        was_shown: bool,
+        old_state: WindowState,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let shown = self.is_shown(client);
+        if was_shown && !shown {
+            self.hide_client(client, old_state)?;
+        } else if !was_shown && shown {
+            self.show_client(client, self.get_window_state(client))?;
+        }
+        Ok(())
+    }
+
+    /// Unmaps the frame and the app. The unmaps are expected, so the client is not withdrawn by them.
+    fn hide_client(
+        &mut self,
+        client: Client<Window>,
+        old_state: WindowState,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // the app of a shaded client is already unmapped
+        if !old_state.contains(StateFlag::Shaded) {
+            self.expect_unmap(client);
+            self.session.connection().unmap_window(client.app_id)?;
+        }
+        self.session.connection().unmap_window(client.frame_id)?;
+        self.set_wm_state(client, WmState::Iconic)?;
+        self.unfocus_client(client)?;
+        Ok(())
+    }
+
+    fn show_client(
+        &mut self,
+        client: Client<Window>,
+        window_state: WindowState,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.session.connection().map_window(client.frame_id)?;
+        if !window_state.contains(StateFlag::Shaded) {
+            self.session.connection().map_window(client.app_id)?;
+        }
+        self.set_wm_state(client, WmState::Normal)?;
+        Ok(())
+    }
+
+    pub fn get_desktop(&self, client: Client<Window>) -> Desktop {
+        self.desktops
+            .query(client)
+            .copied()
+            .unwrap_or(Desktop::Index(self.workspaces.current()))
+    }
+
+    /// Publishes the desktops, and the work area for each of them.
+    pub fn init_workspaces(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        // the current desktop before a restart
+        let property = self
+            .session
+            .connection()
+            .get_property(
+                true,
+                self.session.screen().root,
+                self.session.atoms()._CLEARWM_RESTART_STATE,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )?
+            .reply()?;
+        if let Some(current) = property.value32().and_then(|mut values| values.next()) {
+            self.workspaces.switch_to(current);
+        }
+        ewmh::set_workspaces(self.session, &self.workspaces)?;
+        ewmh::set_work_area(
+            self.session,
+            self.monitors.bounding_work_area(),
+            self.workspaces.count(),
+        )?;
+        Ok(())
+    }
+
+    /// Reads the _NET_WM_DESKTOP set by the client before it is mapped, and moves it there.
+    /// Clients without a valid desktop are put on the current one.
+    pub fn load_desktop(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let property = self
+            .session
+            .connection()
+            .get_property(
+                false,
+                client.app_id,
+                self.session.atoms()._NET_WM_DESKTOP,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )?
+            .reply()?;
+        let desktop = property
+            .value32()
+            .and_then(|mut values| values.next())
+            .map(Desktop::from_value)
+            .filter(|desktop| self.workspaces.contains(*desktop))
+            .unwrap_or_else(|| {
+                if self.get_window_type(client).is_on_all_desktops() {
+                    Desktop::All
+                } else {
+                    Desktop::Index(self.workspaces.current())
+                }
+            });
+        self.move_client_to_desktop(client, desktop)
+    }
+
+    /// Moves the client to the desktop, hiding it if the desktop is not the current one.
+    /// Requests for desktops which do not exist are ignored.
+    pub fn move_client_to_desktop(
+        &mut self,
+        client: Client<Window>,
+        desktop: Desktop,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.workspaces.contains(desktop) {
+            return Ok(());
+        }
+        let was_shown = self.is_shown(client);
+        self.desktops.insert(client, desktop);
+        ewmh::set_wm_desktop(self.session, client.app_id, desktop)?;
+
+        // only a client on every desktop is sticky, as in set_window_state
+        let window_state = self.get_window_state(client);
+        let sticky = desktop == Desktop::All;
+        if window_state.contains(StateFlag::Sticky) != sticky {
+            let window_state = window_state.with(StateFlag::Sticky, sticky);
+            self.window_states.insert(client, window_state);
+            self.publish_window_state(client, window_state)?;
+        }
+        self.update_visibility(client, was_shown, self.get_window_state(client))
+    }
+
+    pub fn switch_desktop(&mut self, index: u32) -> Result<(), Box<dyn std::error::Error>> {
+        if index >= self.workspaces.count() || index == self.workspaces.current() {
+            return Ok(());
+        }
+        self.change_workspaces(|workspaces| {
+            workspaces.switch_to(index);
+        })
+    }
+
+    /// Adds or removes desktops from the end, as requested by a pager.
+    pub fn set_desktop_count(&mut self, count: u32) -> Result<(), Box<dyn std::error::Error>> {
+        if count == 0 {
+            return Ok(());
+        }
+        self.change_workspaces(|workspaces| workspaces.set_count(count))
+    }
+
+    /// Changes the desktops, and shows the clients on the current one.
+    /// The clients on removed desktops are moved to the last one.
+    fn change_workspaces(
+        &mut self,
+        change: impl FnOnce(&mut Workspaces),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let clients = self
+            .client_container
+            .iter_stacking()
+            .map(|client| (*client, self.is_shown(*client)))
+            .collect::<Vec<_>>();
+
+        change(&mut self.workspaces);
+        ewmh::set_workspaces(self.session, &self.workspaces)?;
+        ewmh::set_work_area(
+            self.session,
+            self.monitors.bounding_work_area(),
+            self.workspaces.count(),
+        )?;
+        for (client, _) in &clients {
+            let desktop = self.get_desktop(*client);
+            let clamped_desktop = self.workspaces.clamp(desktop);
+            if clamped_desktop != desktop {
+                self.desktops.insert(*client, clamped_desktop);
+                ewmh::set_wm_desktop(self.session, client.app_id, clamped_desktop)?;
+            }
+        }
+
+        // the clients are hidden first, so the focus does not move to the clients to hide
+        for (client, was_shown) in &clients {
+            if *was_shown && !self.is_shown(*client) {
+                self.hide_client(*client, self.get_window_state(*client))?;
+            }
+        }
+        for (client, was_shown) in &clients {
+            if !*was_shown && self.is_shown(*client) {
+                self.show_client(*client, self.get_window_state(*client))?;
+            }
+        }
+
+        if self.focus_state.focused().is_none() {
+            self.focus_top_client()?;
+        }
+        Ok(())
+    }
+
+    /// Focuses the topmost client shown, after the focused client has been hidden.
+    fn focus_top_client(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let top_client = self
+            .client_container
+            .iter_stacking()
+            .rev()
+            .find(|client| self.is_shown(**client) && self.get_window_type(**client).is_focusable())
+            .copied();
+        if let Some(client) = top_client {
+            self.focus_client(client, None)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_minimized(&self, client: Client<Window>) -> bool {
+        self.get_window_state(client).contains(StateFlag::Hidden)
+    }
+
+    pub fn minimize_client(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let window_state = self.get_window_state(client).with(StateFlag::Hidden, true);
+        self.set_window_state(client, window_state)
+    }
+
+    /// Maps the minimized client again.
+    pub fn restore_client(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.is_minimized(client) {
+            return Ok(());
+        }
+        let window_state = self.get_window_state(client).with(StateFlag::Hidden, false);
+        self.set_window_state(client, window_state)?;
+        self.raise_client(client, None)?;
+        Ok(())
+    }
+
+    /// Maximizes the client in both directions, or restores it if it is maximized in any direction.
+    pub fn toggle_maximize_client(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let window_state = self.get_window_state(client);
+        let maximize = !window_state.contains(StateFlag::MaximizedVert)
+            && !window_state.contains(StateFlag::MaximizedHorz);
+        let window_state = window_state
+            .with(StateFlag::MaximizedVert, maximize)
+            .with(StateFlag::MaximizedHorz, maximize);
+        self.set_window_state(client, window_state)
+    }
+
+    /// Whether the client lists the protocol in WM_PROTOCOLS, which is cached in the hints.
+    fn supports_protocol(&self, client: Client<Window>, protocol: Protocol) -> bool {
+        self.hints_cache
+            .query(client)
+            .is_some_and(|hints| hints.supports(protocol))
+    }
+
+    /// Asks the client to close by WM_DELETE_WINDOW, or kills it if the protocol is not supported.
+    /// The client asked again after it has stopped responding is killed.
+    pub fn close_client(
+        &mut self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.supports_protocol(client, Protocol::Ping) {
+            let mut ping_state = self.pings.query(client).copied().unwrap_or_default();
+            let kill = ping_state.request_close();
+            self.pings.insert(client, ping_state);
+            if kill {
+                info!("{:?} is not responding, killing it", client);
+                return self.force_kill_client(client);
+            }
+        }
+
+        let atoms = self.session.atoms();
+        if !self.supports_protocol(client, Protocol::DeleteWindow) {
+            info!("{:?} does not support WM_DELETE_WINDOW, killing it", client);
+            self.session.connection().kill_client(client.app_id)?;
+            return Ok(());
+        }
+
+        let event = ClientMessageEvent::new(
+            32,
+            client.app_id,
+            atoms.WM_PROTOCOLS,
+            [atoms.WM_DELETE_WINDOW, x11rb::CURRENT_TIME, 0, 0, 0],
+        );
+        self.session
+            .connection()
+            .send_event(false, client.app_id, EventMask::NO_EVENT, event)?;
+
+        if let Some(seconds) = self.session.config().close_config.force_kill_after {
+            // keep the first deadline if the close button is pressed repeatedly
+            if self.close_deadlines.query(client).is_none() {
+                let timer = self.scheduler.schedule_once(
+                    Instant::now() + Duration::from_secs(seconds),
+                    TimerTask::ForceKill(client),
+                );
+                self.close_deadlines.insert(client, timer);
+            }
+        }
+        // a hung client cannot close itself, so it is shown as not responding
+        self.ping_client(client, Instant::now())
+    }
+
+    /// Sends _NET_WM_PING to the client if it supports the protocol.
+    fn ping_client(
+        &mut self,
+        client: Client<Window>,
+        now: Instant,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let atoms = self.session.atoms();
+        if !self.supports_protocol(client, Protocol::Ping) {
+            return Ok(());
+        }
+        let event = ClientMessageEvent::new(
+            32,
+            client.app_id,
+            atoms.WM_PROTOCOLS,
+            [atoms._NET_WM_PING, x11rb::CURRENT_TIME, client.app_id, 0, 0],
+        );
+        self.session
+            .connection()
+            .send_event(false, client.app_id, EventMask::NO_EVENT, event)?;
+
+        if self.pings.query(client).is_none() {
+            self.pings.insert(client, PingState::new());
+        }
+        // the first ping not answered yet keeps its timeout
+        if self.ping_timeouts.query(client).is_none() {
+            let timeout = Duration::from_secs(self.session.config().ping_config.timeout);
+            let timer = self
+                .scheduler
+                .schedule_once(now + timeout, TimerTask::PingTimeout(client));
+            self.ping_timeouts.insert(client, timer);
+        }
+        Ok(())
+    }
 
-pub struct ClientExecutor<'a> {
-    session: &'a X11Session,
-    client_container: ClientContainer<Window>,
-    surface_container: ClientMap<Window, CairoSurface>,
-    draw_queue: ClientMap<Window, ()>,
-    move_resize_queue: ClientMap<Window, ClientGeometry>,
-    hints_cache: ClientMap<Window, ClientHints>,
-}
+    /// Handles the reply to _NET_WM_PING, which the client sends back to the root window.
+    pub fn handle_pong(&mut self, client: Client<Window>) {
+        if let Some(timer) = self.ping_timeouts.query(client) {
+            self.scheduler.cancel(*timer);
+            self.ping_timeouts.remove(client);
+        }
+        // the client answering the ping sent on the close request is alive,
+        // and may be asking the user whether to close, so it is not killed
+        if let Some(timer) = self.close_deadlines.query(client) {
+            info!("{:?} is responding after the close request", client);
+            self.scheduler.cancel(*timer);
+            self.close_deadlines.remove(client);
+        }
+        if let Some(mut ping_state) = self.pings.query(client).copied() {
+            if ping_state.pong() {
+                info!("{:?} is responding again", client);
+                self.queue_draw(client);
+            }
+            self.pings.insert(client, ping_state);
+        }
+    }
 
-pub enum ClientRaisedResult {
-    Raised,
-    NotChanged,
-}
+    pub fn is_responding(&self, client: Client<Window>) -> bool {
+        self.pings
+            .query(client)
+            .is_none_or(|ping_state| ping_state.is_responding())
+    }
 
-impl<'a> ClientExecutor<'a> {
-    pub fn new(session: &'a X11Session) -> Self {
-        Self {
-            session,
-            client_container: ClientContainer::new(),
-            surface_container: ClientMap::new(),
-            draw_queue: ClientMap::new(),
-            move_resize_queue: ClientMap::new(),
-            hints_cache: ClientMap::new(),
+    /// Kills the process of the client by _NET_WM_PID, and closes its connection to the X11 server.
+    pub fn force_kill_client(
+        &self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let connection = self.session.connection();
+        let (pid, client_machine) = self
+            .hints_cache
+            .query(client)
+            .map(|hints| (hints.pid, hints.client_machine.clone()))
+            .unwrap_or_default();
+
+        // the pid is meaningful only on the machine the client runs on
+        let is_local = client_machine.as_deref() == Some(&*uname().nodename().to_string_lossy());
+        if let (Some(pid), true) = (pid.and_then(|pid| Pid::from_raw(pid as i32)), is_local) {
+            info!("Killing the process {:?} of {:?}", pid, client);
+            if let Err(e) = kill_process(pid, Signal::Kill) {
+                error!("Failed to kill the process {:?}: {}", pid, e);
+            }
         }
+
+        connection.kill_client(client.app_id)?;
+        Ok(())
     }
 
-    pub fn container(&self) -> &ClientContainer<Window> {
-        &self.client_container
+    /// The earliest time when handle_deadlines has something to do.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.scheduler.next_deadline()
     }
 
-    pub fn update_hints(
+    pub fn handle_deadlines(&mut self, now: Instant) -> Result<(), Box<dyn std::error::Error>> {
+        for task in self.scheduler.expire(now) {
+            match task {
+                TimerTask::PingClients => {
+                    let clients = self.client_container.iter().copied().collect::<Vec<_>>();
+                    for client in clients {
+                        self.ping_client(client, now)?;
+                    }
+                }
+                TimerTask::PingTimeout(client) => {
+                    self.ping_timeouts.remove(client);
+                    if let Some(mut ping_state) = self.pings.query(client).copied() {
+                        if ping_state.time_out() {
+                            info!("{:?} is not responding", client);
+                            self.queue_draw(client);
+                        }
+                        self.pings.insert(client, ping_state);
+                    }
+                }
+                TimerTask::SyncTimeout(client) => {
+                    // the held resizes are flushed without waiting for the client any longer
+                    if let Some(mut sync_counter) = self.sync_counters.query(client).copied() {
+                        info!("{:?} did not answer the sync request in time", client);
+                        sync_counter.state.time_out();
+                        sync_counter.timeout = None;
+                        self.sync_counters.insert(client, sync_counter);
+                    }
+                }
+                TimerTask::ForceKill(client) => {
+                    self.close_deadlines.remove(client);
+                    // the client is still managed, as the timer is cancelled with the client
+                    info!("{:?} did not close in time", client);
+                    self.force_kill_client(client)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Restarts the timer to ping every client by ping.interval.
+    fn schedule_pings(&mut self) {
+        if let Some(timer) = self.ping_timer.take() {
+            self.scheduler.cancel(timer);
+        }
+        if let Some(seconds) = self.session.config().ping_config.interval {
+            let interval = Duration::from_secs(seconds);
+            self.ping_timer = Some(self.scheduler.schedule_repeating(
+                Instant::now() + interval,
+                interval,
+                TimerTask::PingClients,
+            ));
+        }
+    }
+
+    /// Reads _NET_WM_SYNC_REQUEST_COUNTER of the client supporting _NET_WM_SYNC_REQUEST,
+    /// and sets an alarm on the counter.
+    pub fn update_sync_counter(
         &mut self,
         client: Client<Window>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let hints_cache = self.fetch_hints(client)?;
-        self.hints_cache.insert(client, hints_cache);
+        if !self.session.sync_supported() {
+            return Ok(());
+        }
+        let connection = self.session.connection();
+        let atoms = self.session.atoms();
+        let counter_property = connection
+            .get_property(
+                false,
+                client.app_id,
+                atoms._NET_WM_SYNC_REQUEST_COUNTER,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )?
+            .reply()?;
+        let supports_sync_request = self.supports_protocol(client, Protocol::SyncRequest);
+        let counter = counter_property
+            .value32()
+            .and_then(|mut values| values.next())
+            .filter(|_| supports_sync_request);
+
+        let current = self.sync_counters.query(client).copied();
+        if current.map(|sync_counter| sync_counter.counter) == counter {
+            return Ok(());
+        }
+        self.remove_sync_counter(client)?;
+        let counter = if let Some(counter) = counter {
+            counter
+        } else {
+            return Ok(());
+        };
+
+        // the counter is given by the client, and may not exist,
+        // in which case the client is resized without waiting
+        let counter_value = match connection.sync_query_counter(counter)?.reply() {
+            Ok(reply) => reply.counter_value,
+            Err(ReplyError::X11Error(e)) => {
+                info!("Ignoring the sync counter of {:?}: {:?}", client, e);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let alarm = connection.generate_id()?;
+        let created = connection
+            .sync_create_alarm(
+                alarm,
+                &CreateAlarmAux::default()
+                    .counter(counter)
+                    .value_type(VALUETYPE::ABSOLUTE)
+                    .value(counter_value)
+                    .test_type(TESTTYPE::POSITIVE_COMPARISON)
+                    .events(1),
+            )?
+            .check();
+        match created {
+            Ok(()) => {}
+            Err(ReplyError::X11Error(e)) => {
+                info!("Failed to create the sync alarm of {:?}: {:?}", client, e);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        }
+        self.sync_counters.insert(
+            client,
+            SyncCounter {
+                counter,
+                alarm,
+                state: SyncState::new(from_int64(counter_value)),
+                timeout: None,
+            },
+        );
         Ok(())
     }
 
-    fn fetch_hints(
-        &self,
+    /// Destroys the alarm on the counter of the client, and stops waiting for it.
+    fn remove_sync_counter(
+        &mut self,
         client: Client<Window>,
-    ) -> Result<ClientHints, Box<dyn std::error::Error>> {
-        let title = {
-            let title = self
-                .session
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(sync_counter) = self.sync_counters.query(client).copied() {
+            if let Some(timer) = sync_counter.timeout {
+                self.scheduler.cancel(timer);
+            }
+            self.session
                 .connection()
-                .get_property(
-                    false,
-                    client.app_id,
-                    self.session.atoms().WM_NAME,
-                    self.session.atoms().UTF8_STRING,
-                    0,
-                    1024,
-                )?
-                .reply()?;
+                .sync_destroy_alarm(sync_counter.alarm)?;
+            self.sync_counters.remove(client);
+        }
+        Ok(())
+    }
 
-            if title.value_len != 0 {
-                String::from_utf8(title.value)?
-            } else {
-                let title = self
-                    .session
-                    .connection()
-                    .get_property(
-                        false,
-                        client.app_id,
-                        self.session.atoms().WM_NAME,
-                        self.session.atoms().STRING,
-                        0,
-                        1024,
-                    )?
-                    .reply()?;
-
-                if title.value_len != 0 {
-                    String::from_utf8(title.value)?
-                } else {
-                    String::from("")
+    /// Asks the client to update its counter after it has repainted for the resize.
+    fn send_sync_request(
+        &mut self,
+        client: Client<Window>,
+        now: Instant,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sync_counter = if let Some(sync_counter) = self.sync_counters.query(client) {
+            *sync_counter
+        } else {
+            return Ok(());
+        };
+        let value = sync_counter.state.request();
+        if let Some(timer) = sync_counter.timeout {
+            self.scheduler.cancel(timer);
+        }
+        sync_counter.timeout = Some(
+            self.scheduler
+                .schedule_once(now + SYNC_TIMEOUT, TimerTask::SyncTimeout(client)),
+        );
+        self.sync_counters.insert(client, sync_counter);
+
+        let connection = self.session.connection();
+        let atoms = self.session.atoms();
+        let event = ClientMessageEvent::new(
+            32,
+            client.app_id,
+            atoms.WM_PROTOCOLS,
+            [
+                atoms._NET_WM_SYNC_REQUEST,
+                x11rb::CURRENT_TIME,
+                value as u32,
+                (value >> 32) as u32,
+                0,
+            ],
+        );
+        connection.send_event(false, client.app_id, EventMask::NO_EVENT, event)?;
+        connection.sync_change_alarm(
+            sync_counter.alarm,
+            &ChangeAlarmAux::default().value(to_int64(value)).events(1),
+        )?;
+        Ok(())
+    }
+
+    /// Handles the alarm on the counter of a client, which has repainted after a resize.
+    pub fn handle_sync_alarm(&mut self, alarm: Alarm, counter_value: Int64) {
+        let client = self
+            .sync_counters
+            .iter()
+            .find(|(_, sync_counter)| sync_counter.alarm == alarm)
+            .map(|(client, _)| *client);
+        if let Some(client) = client {
+            if let Some(mut sync_counter) = self.sync_counters.query(client).copied() {
+                if sync_counter.state.complete(from_int64(counter_value)) {
+                    if let Some(timer) = sync_counter.timeout.take() {
+                        self.scheduler.cancel(timer);
+                    }
                 }
+                self.sync_counters.insert(client, sync_counter);
             }
-        };
-        Ok(ClientHints { title })
+        }
     }
 
-    pub fn add_client(
+    fn is_waiting_for_sync(&self, client: Client<Window>) -> bool {
+        self.sync_counters
+            .query(client)
+            .is_some_and(|sync_counter| sync_counter.state.is_waiting())
+    }
+
+    /// Updates the titlebar button under the cursor and redraws the frames that changed.
+    pub fn hover_titlebar_button(&mut self, target: Option<(Client<Window>, TitlebarButton)>) {
+        for client in self.button_state.hover(target) {
+            self.queue_draw(client);
+        }
+    }
+
+    pub fn press_titlebar_button(&mut self, client: Client<Window>, button: TitlebarButton) {
+        self.button_state.press(client, button);
+        self.queue_draw(client);
+    }
+
+    pub fn is_titlebar_button_pressed(&self) -> bool {
+        self.button_state.is_pressed()
+    }
+
+    /// Releases the pressed titlebar button.
+    /// Returns the button to activate if it was released on the pressed button.
+    pub fn release_titlebar_button(
         &mut self,
-        app_id: Window,
-        frame_id: Window,
-        client_geometry: ClientGeometry,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let frame_geometry = client_geometry.parse_as_frame();
-        let surface = self
-            .session
-            .cairo_session()
-            .create_cairo_surface_for_window(
-                self.session,
-                frame_id,
-                frame_geometry.width as i32,
-                frame_geometry.height as i32,
-            )?;
+        target: Option<(Client<Window>, TitlebarButton)>,
+    ) -> Option<(Client<Window>, TitlebarButton)> {
+        if let Some(client) = self.button_state.pressed_client() {
+            self.queue_draw(client);
+        }
+        self.button_state.release(target)
+    }
 
-        let client = self.client_container.add_client(app_id, frame_id);
+    fn focus_client(
+        &mut self,
+        client: Client<Window>,
+        user_time: Option<u32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(previous_client) = self.focus_state.focused() {
+            // The previous client is drawn as inactive.
+            self.queue_draw(previous_client);
+        }
+        self.focus_state.focus(client, user_time);
+        self.queue_draw(client);
 
-        self.surface_container.insert(client, surface);
+        // Focus the client's application window.
+        self.session.connection().set_input_focus(
+            InputFocus::POINTER_ROOT,
+            client.app_id,
+            x11rb::CURRENT_TIME,
+        )?;
+        ewmh::set_active_window(self.session, Some(client.app_id))?;
 
+        let window_state = self.get_window_state(client);
+        if window_state.contains(StateFlag::DemandsAttention) {
+            self.set_window_state(
+                client,
+                window_state.with(StateFlag::DemandsAttention, false),
+            )?;
+        }
         Ok(())
     }
 
-    pub fn remove_client(&mut self, client: Client<Window>) {
-        self.client_container.remove_client(client);
-        self.surface_container.remove(client);
-        self.draw_queue.remove(client);
-        self.move_resize_queue.remove(client);
-        self.hints_cache.remove(client);
+    /// Moves the focus to the previously focused client if the client has the focus.
+    fn unfocus_client(&mut self, client: Client<Window>) -> Result<(), Box<dyn std::error::Error>> {
+        let was_focused = self.focus_state.focused() == Some(client);
+        let next_client = self.focus_state.unfocus(client);
+        if !was_focused {
+            return Ok(());
+        }
+
+        if let Some(next_client) = next_client {
+            self.focus_client(next_client, None)?;
+        } else {
+            self.session.connection().set_input_focus(
+                InputFocus::POINTER_ROOT,
+                InputFocus::POINTER_ROOT,
+                x11rb::CURRENT_TIME,
+            )?;
+            ewmh::set_active_window(self.session, None)?;
+        }
+        Ok(())
     }
 
-    fn get_focused_client(&self) -> Result<Option<Client<Window>>, Box<dyn std::error::Error>> {
-        let focused_window = self.session.connection().get_input_focus()?.reply()?.focus;
+    /// Handles a _NET_ACTIVE_WINDOW request.
+    /// The request of an application is denied if the user has focused another client after it,
+    /// and the client is marked as demanding attention instead.
+    pub fn activate_client(
+        &mut self,
+        client: Client<Window>,
+        source: ActivationSource,
+        time: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.focus_state.allows_activation(source, time) {
+            info!("Denied the activation of {:?} from {:?}", client, source);
+            let window_state = self.get_window_state(client);
+            self.set_window_state(client, window_state.with(StateFlag::DemandsAttention, true))?;
+            return Ok(());
+        }
 
-        Ok(self.client_container.query_client_from_app(focused_window))
+        // the desktop of the client is shown
+        if let Desktop::Index(index) = self.get_desktop(client) {
+            self.switch_desktop(index)?;
+        }
+        self.restore_client(client)?;
+        // pagers act on behalf of the user
+        let user_time = (source == ActivationSource::Pager && time != 0).then_some(time);
+        self.raise_client(client, user_time)?;
+        Ok(())
     }
 
     // If the client is already raised, return false
+    // `user_time` is the time of the user action which raises the client, if any.
     pub fn raise_client(
-        &self,
+        &mut self,
         client: Client<Window>,
+        user_time: Option<u32>,
     ) -> Result<ClientRaisedResult, Box<dyn std::error::Error>> {
-        if let Some(previous_client) = self.get_focused_client()? {
-            if previous_client == client {
+        // e.g. the MapNotify of a client hidden right after it is managed
+        if !self.is_shown(client) {
+            return Ok(ClientRaisedResult::NotChanged);
+        }
+        // clients which do not take the focus are only raised
+        if self.get_window_type(client).is_focusable() {
+            if self.focus_state.focused() == Some(client) {
                 return Ok(ClientRaisedResult::NotChanged);
             }
-            // If there is a previous client, move the frame to the above of the stack to hide application window.
+            self.focus_client(client, user_time)?;
+        }
+
+        self.client_container.raise_client(client);
+        self.restack_clients()?;
+
+        Ok(ClientRaisedResult::Raised)
+    }
+
+    /// Stacks the frames by the layers of the clients, keeping the order in each layer.
+    /// The layers of some window types are pinned, and are not changed by the states.
+    fn restack_clients(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let window_states = &self.window_states;
+        let window_types = &self.window_types;
+        self.client_container.sort_stacking_by_key(|client| {
+            window_types
+                .query(*client)
+                .and_then(|window_type| window_type.layer())
+                .unwrap_or_else(|| {
+                    window_states
+                        .query(*client)
+                        .copied()
+                        .unwrap_or_default()
+                        .layer()
+                })
+        });
+
+        // Move the frames to the above of the stack from the bottom, the application windows follow them.
+        for client in self.client_container.iter_stacking() {
             self.session.connection().configure_window(
-                previous_client.frame_id,
-                &ConfigureWindowAux::default()
-                    .stack_mode(x11rb::protocol::xproto::StackMode::ABOVE),
+                client.frame_id,
+                &ConfigureWindowAux::default().stack_mode(StackMode::ABOVE),
             )?;
         }
+        self.update_client_lists()
+    }
 
-        // Focus the client's application window.
-        self.session.connection().set_input_focus(
-            x11rb::protocol::xproto::InputFocus::POINTER_ROOT,
-            client.app_id,
-            x11rb::CURRENT_TIME,
-        )?;
+    /// The latest geometry of the client, including the queued one.
+    pub fn get_client_geometry(&self, client: Client<Window>) -> ClientGeometry {
+        self.move_resize_queue
+            .query(client)
+            .or_else(|| self.geometries.query(client))
+            .copied()
+            // not reached for managed clients, whose geometry is set by add_client
+            .unwrap_or_else(|| {
+                ClientGeometry::from_app(0, 0, 1, 1, self.session.config().frame_config)
+            })
+    }
 
-        // Move the frame and the application window to the above of the stack.
-        self.session.connection().configure_window(
-            client.frame_id,
-            &ConfigureWindowAux::default().stack_mode(x11rb::protocol::xproto::StackMode::ABOVE),
-        )?;
-        self.session.connection().configure_window(
-            client.app_id,
-            &ConfigureWindowAux::default().stack_mode(x11rb::protocol::xproto::StackMode::ABOVE),
-        )?;
+    /// Applies the current frame configuration of the session to every client.
+    /// Frames and their surfaces are resized and redrawn on the next flush.
+    pub fn reconfigure_clients(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.schedule_pings();
 
-        Ok(ClientRaisedResult::Raised)
-    }
+        let names = self.session.config().workspace_config.names.clone();
+        if names != self.workspaces.names() {
+            self.change_workspaces(|workspaces| workspaces.set_names(names))?;
+        }
 
-    pub fn get_client_geometry(
-        &self,
-        client: Client<Window>,
-    ) -> Result<ClientGeometry, Box<dyn std::error::Error>> {
-        let x11_app_geometry = self
-            .session
-            .connection()
-            .get_geometry(client.app_id)?
-            .reply()?;
+        let clients = self.client_container.iter().copied().collect::<Vec<_>>();
 
-        Ok(ClientGeometry::from_app(
-            x11_app_geometry.x as i32,
-            x11_app_geometry.y as i32,
-            x11_app_geometry.width as u32,
-            x11_app_geometry.height as u32,
-            self.session.config().frame_config,
-        ))
+        for client in clients {
+            let frame_config = self.client_frame_config(client);
+            let window_state = self.get_window_state(client);
+            if let Some(normal_geometry) = self.restore_geometries.query(client).copied() {
+                let normal_geometry = normal_geometry.with_frame_config(frame_config);
+                self.restore_geometries.insert(client, normal_geometry);
+                self.queue_move_resize(
+                    client,
+                    self.state_geometry(client, window_state, normal_geometry),
+                );
+            } else {
+                let client_geometry = self.get_client_geometry(client);
+                self.queue_move_resize(client, client_geometry.with_frame_config(frame_config));
+            }
+        }
+        Ok(())
     }
 
     pub fn flush_queued(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        for (client, client_geometry) in self.move_resize_queue.iter() {
+        let now = Instant::now();
+        let move_resize_queue = std::mem::replace(&mut self.move_resize_queue, ClientMap::new());
+        for (client, client_geometry) in move_resize_queue.iter() {
+            // the resize is held until the client has repainted for the previous one
+            if self.is_waiting_for_sync(*client) {
+                self.move_resize_queue.insert(*client, *client_geometry);
+                continue;
+            }
+            let app_geometry = client_geometry.parse_as_app();
+            let resized = self.geometries.query(*client).map(|geometry| {
+                let current = geometry.parse_as_app();
+                (current.width, current.height)
+            }) != Some((app_geometry.width, app_geometry.height));
+            if resized {
+                self.send_sync_request(*client, now)?;
+            }
             self.move_resize_with_client_geometry(*client, *client_geometry)?;
+            self.queue_draw(*client);
         }
 
         for (client, _) in self.draw_queue.iter() {
+            // drawn after the held geometry is applied to the surface
+            if self.move_resize_queue.query(*client).is_some() {
+                continue;
+            }
             self.draw_client(*client)?;
         }
         self.draw_queue.clear();
-        self.move_resize_queue.clear();
         Ok(())
     }
 
@@ -218,11 +1848,31 @@ impl<'a> ClientExecutor<'a> {
             &hint_default
         };
 
+        let window_state = self.get_window_state(client);
+        // the frame is hidden behind the app
+        if window_state.contains(StateFlag::Fullscreen)
+            || !self.get_window_type(client).is_decorated()
+        {
+            return Ok(());
+        }
+
+        let state = if !self.is_responding(client) {
+            FrameState::NotResponding
+        } else if self.focus_state.focused() == Some(client) {
+            FrameState::Active
+        } else if window_state.contains(StateFlag::DemandsAttention) || hints.wm_hints.urgent {
+            FrameState::Urgent
+        } else {
+            FrameState::Inactive
+        };
+
         let ctx = surface.context()?;
         FrameDrawContext::new(ctx).draw(
-            &self.get_client_geometry(client)?,
-            &self.session.config().frame_config,
+            &self.get_client_geometry(client),
+            &self.session.config(),
+            state,
             hints,
+            |button| self.button_state.appearance(client, button),
         )?;
         surface.flush();
 
@@ -249,46 +1899,45 @@ impl<'a> ClientExecutor<'a> {
     }
 
     fn move_with_client_geometry(
-        &self,
+        &mut self,
         client: Client<Window>,
         client_geometry: ClientGeometry,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let app_geometry = client_geometry.parse_as_app();
         let frame_geometry = client_geometry.parse_as_frame();
 
-        self.session.connection().configure_window(
-            client.app_id,
-            &ConfigureWindowAux::default()
-                .x(app_geometry.x)
-                .y(app_geometry.y),
-        )?;
-
+        // the app moves together with its frame
         self.session.connection().configure_window(
             client.frame_id,
             &ConfigureWindowAux::default()
                 .x(frame_geometry.x)
                 .y(frame_geometry.y),
         )?;
+        self.geometries.insert(client, client_geometry);
+        self.send_configure_notify(client)?;
 
         Ok(())
     }
 
     fn move_resize_with_client_geometry(
-        &self,
+        &mut self,
         client: Client<Window>,
         client_geometry: ClientGeometry,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let app_geometry = client_geometry.parse_as_app();
+        let app_draw_area = client_geometry.parse_as_app_draw_area();
 
-        let frame_geometry = client_geometry.parse_as_frame();
+        let frame_geometry = if self.get_window_state(client).contains(StateFlag::Shaded) {
+            client_geometry.parse_as_shaded_frame()
+        } else {
+            client_geometry.parse_as_frame()
+        };
 
         self.session.connection().configure_window(
             client.app_id,
             &ConfigureWindowAux::default()
-                .x(app_geometry.x)
-                .y(app_geometry.y)
-                .width(app_geometry.width)
-                .height(app_geometry.height),
+                .x(app_draw_area.x)
+                .y(app_draw_area.y)
+                .width(app_draw_area.width)
+                .height(app_draw_area.height),
         )?;
 
         self.session.connection().configure_window(
@@ -303,7 +1952,187 @@ impl<'a> ClientExecutor<'a> {
         if let Some(surface) = self.surface_container.query(client) {
             surface.resize(frame_geometry.width as i32, frame_geometry.height as i32)?;
         }
+        // the frame is removed in fullscreen, and changed by reloading the config
+        let extents = client_geometry.frame_extents();
+        if self
+            .geometries
+            .query(client)
+            .map(|geometry| geometry.frame_extents())
+            != Some(extents)
+        {
+            ewmh::set_frame_extents(self.session, client.app_id, extents)?;
+        }
+        self.geometries.insert(client, client_geometry);
+        self.send_configure_notify(client)?;
 
         Ok(())
     }
+
+    /// Tells the app its position on the root, as the real ConfigureNotify has the one on the frame.
+    /// Required by ICCCM 4.1.5 when the window is moved without being resized.
+    pub fn send_configure_notify(
+        &self,
+        client: Client<Window>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let app_geometry = self.get_client_geometry(client).parse_as_app();
+        let event = ConfigureNotifyEvent {
+            response_type: CONFIGURE_NOTIFY_EVENT,
+            sequence: 0,
+            event: client.app_id,
+            window: client.app_id,
+            above_sibling: x11rb::NONE,
+            x: app_geometry.x as i16,
+            y: app_geometry.y as i16,
+            width: app_geometry.width as u16,
+            height: app_geometry.height as u16,
+            border_width: 0,
+            override_redirect: false,
+        };
+        self.session.connection().send_event(
+            false,
+            client.app_id,
+            EventMask::STRUCTURE_NOTIFY,
+            event,
+        )?;
+        Ok(())
+    }
+}
+
+fn from_int64(value: Int64) -> u64 {
+    ((value.hi as u32 as u64) << 32) | value.lo as u64
+}
+
+fn to_int64(value: u64) -> Int64 {
+    Int64 {
+        hi: (value >> 32) as u32 as i32,
+        lo: value as u32,
+    }
+}
+
+fn screen_area(session: &X11Session) -> Geometry {
+    let screen = session.screen();
+    Geometry {
+        x: 0,
+        y: 0,
+        width: screen.width_in_pixels as u32,
+        height: screen.height_in_pixels as u32,
+    }
+}
+
+// 4 MiB, enough for the icons up to 512x512 and the smaller ones
+const MAX_ICON_LENGTH: u32 = 1 << 20;
+
+/// Malformed properties are treated as absent.
+fn optional_property<T>(
+    reply: Result<Option<T>, ReplyError>,
+) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    match reply {
+        Ok(property) => Ok(property),
+        Err(ReplyError::ConnectionError(ParseError(_))) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn first_value32(property: &GetPropertyReply) -> Option<u32> {
+    property.value32().and_then(|mut values| values.next())
+}
+
+fn transient_for_from(property: &GetPropertyReply, window: Window) -> Option<Window> {
+    first_value32(property).filter(|parent| *parent != x11rb::NONE && *parent != window)
+}
+
+fn protocols_from(property: &GetPropertyReply, atoms: &AtomCollection) -> Vec<Protocol> {
+    property
+        .value32()
+        .into_iter()
+        .flatten()
+        .filter_map(|atom| match atom {
+            atom if atom == atoms.WM_DELETE_WINDOW => Some(Protocol::DeleteWindow),
+            atom if atom == atoms._NET_WM_PING => Some(Protocol::Ping),
+            atom if atom == atoms._NET_WM_SYNC_REQUEST => Some(Protocol::SyncRequest),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_icons(property: &GetPropertyReply) -> Vec<Icon> {
+    property
+        .value32()
+        .map(|values| Icon::parse_all(&values.collect::<Vec<_>>()))
+        .unwrap_or_default()
+}
+
+/// Decodes a text property in the encoding given by its type.
+fn decode_text_property(property: &GetPropertyReply, atoms: &AtomCollection) -> Option<String> {
+    if property.format != 8 {
+        return None;
+    }
+    let encoding = if property.type_ == u32::from(AtomEnum::STRING) {
+        TextEncoding::Latin1
+    } else if property.type_ == atoms.COMPOUND_TEXT {
+        TextEncoding::CompoundText
+    } else {
+        // UTF8_STRING, or a type which is most likely UTF-8 nowadays
+        TextEncoding::Utf8
+    };
+    Some(encoding.decode(&property.value))
+}
+
+/// The title in _NET_WM_NAME, or in WM_NAME if it is not set.
+fn title_from(
+    net_wm_name: &GetPropertyReply,
+    wm_name: &GetPropertyReply,
+    atoms: &AtomCollection,
+) -> String {
+    if net_wm_name.type_ == atoms.UTF8_STRING && net_wm_name.format == 8 {
+        return TextEncoding::Utf8.decode(&net_wm_name.value);
+    }
+    decode_text_property(wm_name, atoms).unwrap_or_default()
+}
+
+fn convert_class(wm_class: &WmClass) -> WindowClass {
+    WindowClass {
+        instance: TextEncoding::Latin1.decode(wm_class.instance()),
+        class: TextEncoding::Latin1.decode(wm_class.class()),
+    }
+}
+
+fn convert_wm_hints(wm_hints: &XWmHints) -> WmHints<Window> {
+    WmHints {
+        input: wm_hints.input,
+        initial_state: wm_hints.initial_state.map(|state| match state {
+            WmHintsState::Normal => InitialState::Normal,
+            WmHintsState::Iconic => InitialState::Iconic,
+        }),
+        urgent: wm_hints.urgent,
+        window_group: wm_hints.window_group,
+    }
+}
+
+fn convert_size_hints(wm_size_hints: &WmSizeHints) -> SizeHints {
+    let size = |size: Option<(i32, i32)>| size.map(|(w, h)| (w.max(0) as u32, h.max(0) as u32));
+    SizeHints {
+        min_size: size(wm_size_hints.min_size),
+        max_size: size(wm_size_hints.max_size),
+        base_size: size(wm_size_hints.base_size),
+        size_increment: size(wm_size_hints.size_increment),
+        aspect: wm_size_hints.aspect.map(|(min, max)| {
+            (
+                (min.numerator.max(0) as u32, min.denominator.max(0) as u32),
+                (max.numerator.max(0) as u32, max.denominator.max(0) as u32),
+            )
+        }),
+        gravity: match wm_size_hints.win_gravity {
+            Some(XGravity::NORTH) => Gravity::North,
+            Some(XGravity::NORTH_EAST) => Gravity::NorthEast,
+            Some(XGravity::WEST) => Gravity::West,
+            Some(XGravity::CENTER) => Gravity::Center,
+            Some(XGravity::EAST) => Gravity::East,
+            Some(XGravity::SOUTH_WEST) => Gravity::SouthWest,
+            Some(XGravity::SOUTH) => Gravity::South,
+            Some(XGravity::SOUTH_EAST) => Gravity::SouthEast,
+            Some(XGravity::STATIC) => Gravity::Static,
+            _ => Gravity::NorthWest,
+        },
+    }
 }
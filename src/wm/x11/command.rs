@@ -0,0 +1,77 @@
+use std::os::unix::net::UnixStream;
+
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{Atom, ClientMessageEvent, ConnectionExt, EventMask},
+    xcb_ffi::XCBConnection,
+};
+
+use super::{ipc, session::AtomCollection};
+
+/// Command sent to the running window manager through the control socket,
+/// or as a `_CLEARWM_COMMAND` client message on the root window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Reload,
+    Exit,
+    Restart,
+}
+
+impl Command {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "reload" => Some(Command::Reload),
+            "exit" => Some(Command::Exit),
+            "restart" => Some(Command::Restart),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Reload => "reload",
+            Command::Exit => "exit",
+            Command::Restart => "restart",
+        }
+    }
+
+    pub fn from_atom(atom: Atom, atoms: &AtomCollection) -> Option<Self> {
+        [Command::Reload, Command::Exit, Command::Restart]
+            .into_iter()
+            .find(|command| command.atom(atoms) == atom)
+    }
+
+    fn atom(&self, atoms: &AtomCollection) -> Atom {
+        match self {
+            Command::Reload => atoms._CLEARWM_RELOAD,
+            Command::Exit => atoms._CLEARWM_EXIT,
+            Command::Restart => atoms._CLEARWM_RESTART,
+        }
+    }
+}
+
+/// Sends the command to the window manager running on the default display.
+/// The client message is sent instead if the control socket is not available.
+pub fn send_command(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    if let Ok(stream) = UnixStream::connect(ipc::socket_path()) {
+        return ipc::send_command(stream, command);
+    }
+    let (connection, screen_num) = XCBConnection::connect(None)?;
+    let root = connection.setup().roots[screen_num].root;
+    let atoms = AtomCollection::new(&connection)?.reply()?;
+
+    let event = ClientMessageEvent::new(
+        32,
+        root,
+        atoms._CLEARWM_COMMAND,
+        [command.atom(&atoms), 0, 0, 0, 0],
+    );
+    connection.send_event(
+        false,
+        root,
+        EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+        event,
+    )?;
+    connection.flush()?;
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{
+        Atom, AtomEnum, ConnectionExt, CreateWindowAux, PropMode, Window, WindowClass,
+    },
+    wrapper::ConnectionExt as _,
+};
+
+use crate::model::{
+    client::{
+        geometry::{FrameExtents, Geometry},
+        state::StateFlag,
+        window_type::WindowType,
+    },
+    workspace::{Desktop, Workspaces},
+};
+
+use super::session::{AtomCollection, X11Session};
+
+/// The root window properties of the Extended Window Manager Hints.
+pub struct Ewmh {
+    // _NET_SUPPORTING_WM_CHECK, which tells the clients that an EWMH compliant window manager is running
+    check_window: Window,
+}
+
+impl Ewmh {
+    pub fn setup(session: &X11Session) -> Result<Self, Box<dyn std::error::Error>> {
+        let connection = session.connection();
+        let atoms = session.atoms();
+        let root = session.screen().root;
+
+        let check_window = connection.generate_id()?;
+        connection.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            check_window,
+            root,
+            -1,
+            -1,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::default().override_redirect(1),
+        )?;
+        for window in [root, check_window] {
+            connection.change_property32(
+                PropMode::REPLACE,
+                window,
+                atoms._NET_SUPPORTING_WM_CHECK,
+                AtomEnum::WINDOW,
+                &[check_window],
+            )?;
+        }
+        connection.change_property8(
+            PropMode::REPLACE,
+            check_window,
+            atoms._NET_WM_NAME,
+            atoms.UTF8_STRING,
+            b"clearwm",
+        )?;
+
+        connection.change_property32(
+            PropMode::REPLACE,
+            root,
+            atoms._NET_SUPPORTED,
+            AtomEnum::ATOM,
+            &supported_atoms(atoms),
+        )?;
+        set_client_list(session, &[])?;
+        set_client_list_stacking(session, &[])?;
+        set_active_window(session, None)?;
+
+        Ok(Self { check_window })
+    }
+
+    /// Removes the properties, so the clients do not see a stale window manager.
+    pub fn release(self, session: &X11Session) -> Result<(), Box<dyn std::error::Error>> {
+        let connection = session.connection();
+        let atoms = session.atoms();
+        let root = session.screen().root;
+        for property in [
+            atoms._NET_SUPPORTING_WM_CHECK,
+            atoms._NET_SUPPORTED,
+            atoms._NET_CLIENT_LIST,
+            atoms._NET_CLIENT_LIST_STACKING,
+            atoms._NET_ACTIVE_WINDOW,
+            atoms._NET_WORKAREA,
+            atoms._NET_NUMBER_OF_DESKTOPS,
+            atoms._NET_CURRENT_DESKTOP,
+            atoms._NET_DESKTOP_NAMES,
+        ] {
+            connection.delete_property(root, property)?;
+        }
+        connection.destroy_window(self.check_window)?;
+        Ok(())
+    }
+}
+
+fn supported_atoms(atoms: &AtomCollection) -> Vec<Atom> {
+    let mut supported = vec![
+        atoms._NET_SUPPORTED,
+        atoms._NET_SUPPORTING_WM_CHECK,
+        atoms._NET_CLIENT_LIST,
+        atoms._NET_CLIENT_LIST_STACKING,
+        atoms._NET_ACTIVE_WINDOW,
+        atoms._NET_WORKAREA,
+        atoms._NET_NUMBER_OF_DESKTOPS,
+        atoms._NET_CURRENT_DESKTOP,
+        atoms._NET_DESKTOP_NAMES,
+        atoms._NET_WM_NAME,
+        atoms._NET_WM_DESKTOP,
+        atoms._NET_WM_MOVERESIZE,
+        atoms._NET_WM_PING,
+        atoms._NET_WM_ICON,
+        atoms._NET_WM_SYNC_REQUEST,
+        atoms._NET_WM_SYNC_REQUEST_COUNTER,
+        atoms._NET_FRAME_EXTENTS,
+        atoms._NET_REQUEST_FRAME_EXTENTS,
+        atoms._NET_WM_STRUT,
+        atoms._NET_WM_STRUT_PARTIAL,
+        atoms._NET_WM_STATE,
+        atoms._NET_WM_WINDOW_TYPE,
+    ];
+    supported.extend(StateFlag::ALL.map(|flag| state_atom(atoms, flag)));
+    supported.extend(WindowType::ALL.map(|window_type| window_type_atom(atoms, window_type)));
+    supported
+}
+
+pub fn state_atom(atoms: &AtomCollection, flag: StateFlag) -> Atom {
+    match flag {
+        StateFlag::Fullscreen => atoms._NET_WM_STATE_FULLSCREEN,
+        StateFlag::MaximizedVert => atoms._NET_WM_STATE_MAXIMIZED_VERT,
+        StateFlag::MaximizedHorz => atoms._NET_WM_STATE_MAXIMIZED_HORZ,
+        StateFlag::Above => atoms._NET_WM_STATE_ABOVE,
+        StateFlag::Below => atoms._NET_WM_STATE_BELOW,
+        StateFlag::Sticky => atoms._NET_WM_STATE_STICKY,
+        StateFlag::Hidden => atoms._NET_WM_STATE_HIDDEN,
+        StateFlag::Shaded => atoms._NET_WM_STATE_SHADED,
+        StateFlag::SkipTaskbar => atoms._NET_WM_STATE_SKIP_TASKBAR,
+        StateFlag::SkipPager => atoms._NET_WM_STATE_SKIP_PAGER,
+        StateFlag::DemandsAttention => atoms._NET_WM_STATE_DEMANDS_ATTENTION,
+    }
+}
+
+pub fn state_flag(atoms: &AtomCollection, atom: Atom) -> Option<StateFlag> {
+    StateFlag::ALL
+        .into_iter()
+        .find(|flag| state_atom(atoms, *flag) == atom)
+}
+
+pub fn window_type_atom(atoms: &AtomCollection, window_type: WindowType) -> Atom {
+    match window_type {
+        WindowType::Desktop => atoms._NET_WM_WINDOW_TYPE_DESKTOP,
+        WindowType::Dock => atoms._NET_WM_WINDOW_TYPE_DOCK,
+        WindowType::Toolbar => atoms._NET_WM_WINDOW_TYPE_TOOLBAR,
+        WindowType::Menu => atoms._NET_WM_WINDOW_TYPE_MENU,
+        WindowType::Utility => atoms._NET_WM_WINDOW_TYPE_UTILITY,
+        WindowType::Splash => atoms._NET_WM_WINDOW_TYPE_SPLASH,
+        WindowType::Dialog => atoms._NET_WM_WINDOW_TYPE_DIALOG,
+        WindowType::DropdownMenu => atoms._NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
+        WindowType::PopupMenu => atoms._NET_WM_WINDOW_TYPE_POPUP_MENU,
+        WindowType::Tooltip => atoms._NET_WM_WINDOW_TYPE_TOOLTIP,
+        WindowType::Notification => atoms._NET_WM_WINDOW_TYPE_NOTIFICATION,
+        WindowType::Combo => atoms._NET_WM_WINDOW_TYPE_COMBO,
+        WindowType::Dnd => atoms._NET_WM_WINDOW_TYPE_DND,
+        WindowType::Normal => atoms._NET_WM_WINDOW_TYPE_NORMAL,
+    }
+}
+
+pub fn window_type(atoms: &AtomCollection, atom: Atom) -> Option<WindowType> {
+    WindowType::ALL
+        .into_iter()
+        .find(|window_type| window_type_atom(atoms, *window_type) == atom)
+}
+
+/// Sets the app windows of the managed clients in the order they are mapped.
+pub fn set_client_list(
+    session: &X11Session,
+    apps: &[Window],
+) -> Result<(), Box<dyn std::error::Error>> {
+    session.connection().change_property32(
+        PropMode::REPLACE,
+        session.screen().root,
+        session.atoms()._NET_CLIENT_LIST,
+        AtomEnum::WINDOW,
+        apps,
+    )?;
+    Ok(())
+}
+
+/// Sets the app windows of the managed clients from the bottom of the stacking order.
+pub fn set_client_list_stacking(
+    session: &X11Session,
+    apps: &[Window],
+) -> Result<(), Box<dyn std::error::Error>> {
+    session.connection().change_property32(
+        PropMode::REPLACE,
+        session.screen().root,
+        session.atoms()._NET_CLIENT_LIST_STACKING,
+        AtomEnum::WINDOW,
+        apps,
+    )?;
+    Ok(())
+}
+
+pub fn set_active_window(
+    session: &X11Session,
+    app: Option<Window>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    session.connection().change_property32(
+        PropMode::REPLACE,
+        session.screen().root,
+        session.atoms()._NET_ACTIVE_WINDOW,
+        AtomEnum::WINDOW,
+        &[app.unwrap_or(x11rb::NONE)],
+    )?;
+    Ok(())
+}
+
+/// Sets the area of the screen which is not reserved by the struts of the docks.
+/// The area is the same on every desktop.
+pub fn set_work_area(
+    session: &X11Session,
+    area: Geometry,
+    desktop_count: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let values =
+        [area.x as u32, area.y as u32, area.width, area.height].repeat(desktop_count as usize);
+    session.connection().change_property32(
+        PropMode::REPLACE,
+        session.screen().root,
+        session.atoms()._NET_WORKAREA,
+        AtomEnum::CARDINAL,
+        &values,
+    )?;
+    Ok(())
+}
+
+/// Sets the number, the names and the current one of the desktops.
+pub fn set_workspaces(
+    session: &X11Session,
+    workspaces: &Workspaces,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = session.connection();
+    let atoms = session.atoms();
+    let root = session.screen().root;
+    connection.change_property32(
+        PropMode::REPLACE,
+        root,
+        atoms._NET_NUMBER_OF_DESKTOPS,
+        AtomEnum::CARDINAL,
+        &[workspaces.count()],
+    )?;
+    connection.change_property32(
+        PropMode::REPLACE,
+        root,
+        atoms._NET_CURRENT_DESKTOP,
+        AtomEnum::CARDINAL,
+        &[workspaces.current()],
+    )?;
+    // null-terminated UTF-8 strings
+    let names = workspaces
+        .names()
+        .iter()
+        .flat_map(|name| name.bytes().chain([0]))
+        .collect::<Vec<_>>();
+    connection.change_property8(
+        PropMode::REPLACE,
+        root,
+        atoms._NET_DESKTOP_NAMES,
+        atoms.UTF8_STRING,
+        &names,
+    )?;
+    Ok(())
+}
+
+pub fn set_wm_desktop(
+    session: &X11Session,
+    app: Window,
+    desktop: Desktop,
+) -> Result<(), Box<dyn std::error::Error>> {
+    session.connection().change_property32(
+        PropMode::REPLACE,
+        app,
+        session.atoms()._NET_WM_DESKTOP,
+        AtomEnum::CARDINAL,
+        &[desktop.to_value()],
+    )?;
+    Ok(())
+}
+
+/// Tells the client the widths of its frame, which are also set before it is mapped on request.
+pub fn set_frame_extents(
+    session: &X11Session,
+    window: Window,
+    extents: FrameExtents,
+) -> Result<(), Box<dyn std::error::Error>> {
+    session.connection().change_property32(
+        PropMode::REPLACE,
+        window,
+        session.atoms()._NET_FRAME_EXTENTS,
+        AtomEnum::CARDINAL,
+        &[extents.left, extents.right, extents.top, extents.bottom],
+    )?;
+    Ok(())
+}
+
+pub fn set_window_state(
+    session: &X11Session,
+    app: Window,
+    state_atoms: &[Atom],
+) -> Result<(), Box<dyn std::error::Error>> {
+    session.connection().change_property32(
+        PropMode::REPLACE,
+        app,
+        session.atoms()._NET_WM_STATE,
+        AtomEnum::ATOM,
+        state_atoms,
+    )?;
+    Ok(())
+}
@@ -1,30 +1,61 @@
+use std::time::Instant;
+
 use x11rb::{
     connection::Connection,
+    errors::ReplyError,
     protocol::{
         xproto::{
-            ButtonPressEvent, ButtonReleaseEvent, ChangeWindowAttributesAux, ColormapAlloc,
-            ConfigureRequestEvent, ConfigureWindowAux, ConnectionExt, CreateWindowAux, EventMask,
-            ExposeEvent, MapNotifyEvent, MapRequestEvent, MotionNotifyEvent, PropertyNotifyEvent,
+            ButtonPressEvent, ButtonReleaseEvent, ChangeWindowAttributesAux, ClientMessageEvent,
+            Colormap, ColormapAlloc, ConfigWindow, ConfigureRequestEvent, ConfigureWindowAux,
+            ConnectionExt, CreateWindowAux, DestroyNotifyEvent, EnterNotifyEvent, EventMask,
+            ExposeEvent, GrabMode, GrabStatus, KeyPressEvent, LeaveNotifyEvent, MapNotifyEvent,
+            MapRequestEvent, MapState, MotionNotifyEvent, PropertyNotifyEvent, SelectionClearEvent,
             UnmapNotifyEvent, Window, WindowClass,
         },
         Event,
     },
 };
 
-use log::info;
+use log::{error, info};
 
-use crate::model::client::{drag::DragState, geometry::ClientGeometry};
+use crate::{
+    config::{self, TitlebarButton},
+    model::{
+        client::{
+            drag::{is_button_held, DragState, KeyboardDragAction, MoveResizeRequest},
+            focus::ActivationSource,
+            geometry::{ClientGeometry, FrameExtents, Geometry},
+            state::StateAction,
+            titlebar::shown_button,
+            Client,
+        },
+        workspace::Desktop,
+    },
+};
 
 use super::{
-    client_executor::{ClientExecutor, ClientRaisedResult},
+    client_executor::{ClientExecutor, ClientRaisedResult, WmState},
+    command::Command,
+    ewmh,
     session::X11Session,
+    window_manager::ExitRequest,
 };
 
+/// Active grabs taken for a drag started by _NET_WM_MOVERESIZE, to be released when it ends.
+/// A drag started on the frame has only the implicit grab of the button, which needs no release.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct DragGrabs {
+    pointer: bool,
+    keyboard: bool,
+}
+
 /// Handler processes X11 events and dispatches them to the appropriate client.
 pub struct Handler<'a> {
     session: &'a X11Session,
     drag_state: DragState<Window>,
+    drag_grabs: DragGrabs,
     client_exec: ClientExecutor<'a>,
+    exit_request: Option<ExitRequest>,
 }
 
 impl<'a> Handler<'a> {
@@ -32,35 +63,412 @@ impl<'a> Handler<'a> {
         Self {
             session,
             drag_state: DragState::None,
+            drag_grabs: DragGrabs::default(),
             client_exec: ClientExecutor::new(session),
+            exit_request: None,
         }
     }
 
+    /// Handles the event. Error replies from the X11 server are logged and skipped,
+    /// as the windows of the clients may be destroyed at any time before their events are handled.
     pub fn handle_event(&mut self, event: Event) -> Result<(), Box<dyn std::error::Error>> {
         info!("event: {:?}", event);
+        ignore_x11_error(self.dispatch_event(event), "handle the event")
+    }
+
+    fn dispatch_event(&mut self, event: Event) -> Result<(), Box<dyn std::error::Error>> {
         match event {
-            Event::ClientMessage(_) => {
-                return Ok(());
-            }
+            Event::ClientMessage(event) => self.handle_client_message(event)?,
             Event::Expose(event) => self.handle_expose(event)?,
             Event::ConfigureRequest(event) => self.handle_configure_request(event)?,
             Event::MapRequest(event) => self.handle_map_request(event)?,
             Event::MapNotify(event) => self.handle_map_notify(event)?,
             Event::ButtonPress(event) => self.handle_button_press(event)?,
             Event::ButtonRelease(event) => self.handle_button_release(event)?,
+            Event::KeyPress(event) => self.handle_key_press(event)?,
             Event::MotionNotify(event) => self.handle_motion_notify(event)?,
+            Event::EnterNotify(event) => self.handle_enter_notify(event)?,
+            Event::LeaveNotify(event) => self.handle_leave_notify(event)?,
             Event::UnmapNotify(event) => self.handle_unmap_notify(event)?,
+            Event::DestroyNotify(event) => self.handle_destroy_notify(event)?,
             Event::PropertyNotify(event) => self.handle_property_notify(event)?,
+            Event::SelectionClear(event) => self.handle_selection_clear(event),
+            Event::RandrScreenChangeNotify(_) => self.client_exec.update_monitors()?,
+            Event::SyncAlarmNotify(event) => self
+                .client_exec
+                .handle_sync_alarm(event.alarm, event.counter_value),
             _ => {}
         }
         Ok(())
     }
 
+    pub fn exit_request(&self) -> Option<ExitRequest> {
+        self.exit_request
+    }
+
+    /// Stores the state of the clients on their windows for the restarted window manager.
+    pub fn save_restart_state(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.client_exec.save_restart_state()
+    }
+
+    /// Gives the apps back to the root before exiting.
+    pub fn release_clients(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.client_exec.release_clients()
+    }
+
+    /// The earliest time when handle_deadlines should be called.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.client_exec.next_deadline()
+    }
+
+    pub fn handle_deadlines(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        ignore_x11_error(
+            self.client_exec.handle_deadlines(Instant::now()),
+            "run the timers",
+        )
+    }
+
     pub fn flush_queued(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         self.client_exec.flush_queued()?;
         Ok(())
     }
 
+    fn handle_client_message(
+        &mut self,
+        event: ClientMessageEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if event.type_ == self.session.atoms()._NET_ACTIVE_WINDOW {
+            return self.handle_activate_request(event);
+        }
+        if event.type_ == self.session.atoms()._NET_WM_STATE {
+            return self.handle_state_request(event);
+        }
+        if event.type_ == self.session.atoms()._NET_REQUEST_FRAME_EXTENTS {
+            return self.handle_frame_extents_request(event);
+        }
+        if event.type_ == self.session.atoms()._NET_WM_DESKTOP {
+            return self.handle_desktop_request(event);
+        }
+        if event.type_ == self.session.atoms()._NET_WM_MOVERESIZE {
+            return self.handle_move_resize_request(event);
+        }
+        if event.window == self.session.screen().root {
+            let [value, _, window, ..] = event.data.as_data32();
+            if event.type_ == self.session.atoms().WM_PROTOCOLS
+                && value == self.session.atoms()._NET_WM_PING
+            {
+                if let Some(client) = self.client_exec.container().query_client_from_app(window) {
+                    self.client_exec.handle_pong(client);
+                }
+                return Ok(());
+            }
+            if event.type_ == self.session.atoms()._NET_CURRENT_DESKTOP {
+                return self.client_exec.switch_desktop(value);
+            }
+            if event.type_ == self.session.atoms()._NET_NUMBER_OF_DESKTOPS {
+                return self.client_exec.set_desktop_count(value);
+            }
+        }
+
+        if event.window != self.session.screen().root
+            || event.type_ != self.session.atoms()._CLEARWM_COMMAND
+        {
+            return Ok(());
+        }
+
+        match Command::from_atom(event.data.as_data32()[0], self.session.atoms()) {
+            // the failure is logged, as nobody waits for the result of the client message
+            Some(command) => {
+                let _ = self.handle_command(command)?;
+            }
+            None => info!("unknown command: {:?}", event.data.as_data32()),
+        }
+        Ok(())
+    }
+
+    /// Runs the command from a client message, a signal, or the control socket.
+    /// The inner error is the failure of the command, which is reported to the sender
+    /// without stopping the window manager.
+    pub fn handle_command(
+        &mut self,
+        command: Command,
+    ) -> Result<Result<(), String>, Box<dyn std::error::Error>> {
+        match command {
+            Command::Reload => return self.reload_config(),
+            Command::Exit => self.exit_request = Some(ExitRequest::Exit),
+            Command::Restart => self.exit_request = Some(ExitRequest::Restart),
+        }
+        Ok(Ok(()))
+    }
+
+    fn handle_activate_request(
+        &mut self,
+        event: ClientMessageEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let client = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_app(event.window)
+        {
+            client
+        } else {
+            return Ok(());
+        };
+
+        let [source, time, ..] = event.data.as_data32();
+        self.client_exec
+            .activate_client(client, ActivationSource::from_value(source), time)?;
+        Ok(())
+    }
+
+    /// Sets _NET_FRAME_EXTENTS on the window not mapped yet,
+    /// so the toolkit can place it before the frame is created.
+    fn handle_frame_extents_request(
+        &mut self,
+        event: ClientMessageEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let extents = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_app(event.window)
+        {
+            self.client_exec.get_client_geometry(client).frame_extents()
+        } else {
+            let transient_for = self.client_exec.fetch_transient_for(event.window)?;
+            let window_type = self
+                .client_exec
+                .fetch_window_type(event.window, transient_for.is_some())?;
+            FrameExtents::from_frame_config(self.client_exec.frame_config_for(window_type))
+        };
+        ewmh::set_frame_extents(self.session, event.window, extents)
+    }
+
+    fn handle_desktop_request(
+        &mut self,
+        event: ClientMessageEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let client = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_app(event.window)
+        {
+            client
+        } else {
+            return Ok(());
+        };
+
+        let [desktop, ..] = event.data.as_data32();
+        self.client_exec
+            .move_client_to_desktop(client, Desktop::from_value(desktop))
+    }
+
+    /// Starts dragging the client on behalf of the app, which draws its own titlebar.
+    /// The pointer is grabbed by the frame, as the button has been pressed on the app.
+    fn handle_move_resize_request(
+        &mut self,
+        event: ClientMessageEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let client = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_app(event.window)
+        {
+            client
+        } else {
+            return Ok(());
+        };
+
+        let [x_root, y_root, direction, button, _] = event.data.as_data32();
+        let (geometry_control, by_keyboard) = match MoveResizeRequest::from_value(direction) {
+            Some(MoveResizeRequest::Start(geometry_control)) => (geometry_control, false),
+            Some(MoveResizeRequest::StartByKeyboard(geometry_control)) => (geometry_control, true),
+            Some(MoveResizeRequest::Cancel) => return self.cancel_drag(),
+            None => return Ok(()),
+        };
+        // maximized and fullscreen clients keep the geometry given by the window manager
+        if self.drag_state.dragging().is_some()
+            || self
+                .client_exec
+                .get_window_state(client)
+                .has_fixed_geometry()
+        {
+            return Ok(());
+        }
+
+        let connection = self.session.connection();
+        let client_geometry = self.client_exec.get_client_geometry(client);
+        let start_root_position = if by_keyboard {
+            // the pointer is put on the dragged point, and moved by the keys
+            let app_geometry = client_geometry.parse_as_app();
+            let position = if geometry_control.is_resize() {
+                (
+                    app_geometry.x + app_geometry.width as i32,
+                    app_geometry.y + app_geometry.height as i32,
+                )
+            } else {
+                (
+                    app_geometry.x + app_geometry.width as i32 / 2,
+                    app_geometry.y + app_geometry.height as i32 / 2,
+                )
+            };
+            connection.warp_pointer(
+                x11rb::NONE,
+                self.session.screen().root,
+                0,
+                0,
+                0,
+                0,
+                position.0 as i16,
+                position.1 as i16,
+            )?;
+            position
+        } else {
+            // the button may have been released before the request arrives
+            let pointer = connection
+                .query_pointer(self.session.screen().root)?
+                .reply()?;
+            if !is_button_held(button, u16::from(pointer.mask)) {
+                info!(
+                    "Ignoring the move resize request of {:?}, as button {} is not held",
+                    client, button
+                );
+                return Ok(());
+            }
+            (x_root as i32, y_root as i32)
+        };
+
+        let grab_status = connection
+            .grab_pointer(
+                false,
+                client.frame_id,
+                EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                x11rb::CURRENT_TIME,
+            )?
+            .reply()?
+            .status;
+        if grab_status != GrabStatus::SUCCESS {
+            info!(
+                "Failed to grab the pointer for {:?}: {:?}",
+                client, grab_status
+            );
+            return Ok(());
+        }
+        if by_keyboard {
+            let grab_status = connection
+                .grab_keyboard(
+                    false,
+                    client.frame_id,
+                    x11rb::CURRENT_TIME,
+                    GrabMode::ASYNC,
+                    GrabMode::ASYNC,
+                )?
+                .reply()?
+                .status;
+            if grab_status != GrabStatus::SUCCESS {
+                info!(
+                    "Failed to grab the keyboard for {:?}: {:?}",
+                    client, grab_status
+                );
+                connection.ungrab_pointer(x11rb::CURRENT_TIME)?;
+                return Ok(());
+            }
+        }
+
+        self.client_exec.raise_client(client, None)?;
+        self.drag_grabs = DragGrabs {
+            pointer: true,
+            keyboard: by_keyboard,
+        };
+        self.drag_state = DragState::new_as_dragging(
+            client,
+            geometry_control,
+            start_root_position,
+            client_geometry,
+        );
+        Ok(())
+    }
+
+    /// Finishes the drag, releasing the grabs taken for _NET_WM_MOVERESIZE if any.
+    fn end_drag(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.drag_state.release_from_dragging();
+        let connection = self.session.connection();
+        let drag_grabs = std::mem::take(&mut self.drag_grabs);
+        if drag_grabs.pointer {
+            connection.ungrab_pointer(x11rb::CURRENT_TIME)?;
+        }
+        if drag_grabs.keyboard {
+            connection.ungrab_keyboard(x11rb::CURRENT_TIME)?;
+        }
+        Ok(())
+    }
+
+    /// Puts the client back to the geometry when the drag started.
+    fn cancel_drag(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(drag_detail) = self.drag_state.dragging() {
+            self.client_exec.apply_geometry(
+                drag_detail.client(),
+                drag_detail.start_geometry(),
+                drag_detail.geometry_control().is_resize(),
+            )?;
+        }
+        self.end_drag()
+    }
+
+    fn handle_state_request(
+        &mut self,
+        event: ClientMessageEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let client = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_app(event.window)
+        {
+            client
+        } else {
+            return Ok(());
+        };
+
+        let [action, first, second, ..] = event.data.as_data32();
+        let action = if let Some(action) = StateAction::from_value(action) {
+            action
+        } else {
+            return Ok(());
+        };
+        let flags = [first, second]
+            .into_iter()
+            .filter_map(|atom| ewmh::state_flag(self.session.atoms(), atom))
+            .collect::<Vec<_>>();
+        self.client_exec
+            .change_window_state(client, action, &flags)?;
+        Ok(())
+    }
+
+    fn handle_selection_clear(&mut self, event: SelectionClearEvent) {
+        // another window manager has taken over the screen
+        if event.selection == self.session.manager_selection() {
+            info!("The manager selection is taken by {:?}", event.owner);
+            self.exit_request = Some(ExitRequest::Exit);
+        }
+    }
+
+    fn reload_config(&mut self) -> Result<Result<(), String>, Box<dyn std::error::Error>> {
+        // keep the current config if the new one is broken
+        let window_manager_config = match config::load_config() {
+            Ok(window_manager_config) => window_manager_config,
+            Err(e) => {
+                error!("Failed to reload config: {}", e);
+                return Ok(Err(format!("failed to reload config: {}", e)));
+            }
+        };
+        self.session.set_config(window_manager_config);
+        self.client_exec.reconfigure_clients()?;
+        Ok(Ok(()))
+    }
+
     fn handle_property_notify(
         &mut self,
         event: PropertyNotifyEvent,
@@ -75,8 +483,19 @@ impl<'a> Handler<'a> {
         } else {
             return Ok(());
         };
-        self.client_exec.update_hints(client)?;
-        self.client_exec.queue_draw(client);
+
+        let atoms = self.session.atoms();
+        if event.atom == atoms._NET_WM_STRUT || event.atom == atoms._NET_WM_STRUT_PARTIAL {
+            return self.client_exec.update_strut(client);
+        }
+        if event.atom == atoms._NET_WM_SYNC_REQUEST_COUNTER || event.atom == atoms.WM_PROTOCOLS {
+            // the cached WM_PROTOCOLS tells whether the counter is used
+            self.client_exec.update_hint(client, event.atom)?;
+            return self.client_exec.update_sync_counter(client);
+        }
+        if self.client_exec.update_hint(client, event.atom)? {
+            self.client_exec.queue_draw(client);
+        }
         Ok(())
     }
 
@@ -100,6 +519,11 @@ impl<'a> Handler<'a> {
         &mut self,
         event: ButtonPressEvent,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        // a click finishes the drag by the keyboard
+        if self.drag_state.dragging().is_some() {
+            return self.end_drag();
+        }
+
         // get client if the window is a frame
         let client = if let Some(client) = self
             .client_exec
@@ -112,29 +536,170 @@ impl<'a> Handler<'a> {
         };
 
         // raise client
-        if let ClientRaisedResult::Raised = self.client_exec.raise_client(client)? {
+        let raised = self.client_exec.raise_client(client, Some(event.time))?;
+
+        // the click on the app is propagated to the frame, and only raises the client
+        if event.child == client.app_id {
             return Ok(());
         }
 
-        let geometry_control = self
-            .client_exec
-            .get_client_geometry(client)?
+        // titlebar buttons work on the first click
+        if let Some(button) =
+            self.titlebar_button_at(client, event.event_x as i32, event.event_y as i32)?
+        {
+            self.client_exec.press_titlebar_button(client, button);
+            return Ok(());
+        }
+
+        if let ClientRaisedResult::Raised = raised {
+            return Ok(());
+        }
+
+        let client_geometry = self.client_exec.get_client_geometry(client);
+        let geometry_control = client_geometry
             .check_control_by_position_on_frame(event.event_x as i32, event.event_y as i32);
 
-        // save the start position of cursor for dragging
-        let last_root_position = (event.root_x as i32, event.root_y as i32);
-        self.drag_state = DragState::new_as_dragging(client, geometry_control, last_root_position);
+        // save the start position of cursor and the geometry for dragging
+        let start_root_position = (event.root_x as i32, event.root_y as i32);
+        self.drag_state = DragState::new_as_dragging(
+            client,
+            geometry_control,
+            start_root_position,
+            client_geometry,
+        );
         Ok(())
     }
 
     fn handle_button_release(
         &mut self,
-        _event: ButtonReleaseEvent,
+        event: ButtonReleaseEvent,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        self.drag_state.release_from_dragging();
+        if self.drag_state.dragging().is_some() {
+            // the drag by the keyboard ends by Return, Escape or a click, not by a release
+            if self.drag_grabs.keyboard {
+                return Ok(());
+            }
+            self.end_drag()?;
+        }
+
+        if !self.client_exec.is_titlebar_button_pressed() {
+            return Ok(());
+        }
+
+        // the frame receives the release event by the implicit grab even if the cursor has left it
+        let target = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_frame(event.event)
+        {
+            self.titlebar_button_at(client, event.event_x as i32, event.event_y as i32)?
+                .map(|button| (client, button))
+        } else {
+            None
+        };
+
+        if let Some((client, button)) = self.client_exec.release_titlebar_button(target) {
+            match button {
+                TitlebarButton::Close => self.client_exec.close_client(client)?,
+                TitlebarButton::Maximize => self.client_exec.toggle_maximize_client(client)?,
+                TitlebarButton::Minimize => self.client_exec.minimize_client(client)?,
+                TitlebarButton::ForceKill => {
+                    info!("Killing {:?} by the titlebar button", client);
+                    self.client_exec.force_kill_client(client)?
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Handles the keys while dragging by the keyboard, which has been grabbed by the frame.
+    fn handle_key_press(&mut self, event: KeyPressEvent) -> Result<(), Box<dyn std::error::Error>> {
+        if self.drag_state.dragging().is_none() {
+            return Ok(());
+        }
+
+        let connection = self.session.connection();
+        let keysym = connection
+            .get_keyboard_mapping(event.detail, 1)?
+            .reply()?
+            .keysyms
+            .first()
+            .copied()
+            .unwrap_or(x11rb::NO_SYMBOL);
+        match KeyboardDragAction::from_keysym(keysym) {
+            // the motion of the pointer drives the drag
+            Some(KeyboardDragAction::Step(x, y)) => {
+                connection.warp_pointer(
+                    x11rb::NONE,
+                    x11rb::NONE,
+                    0,
+                    0,
+                    0,
+                    0,
+                    x as i16,
+                    y as i16,
+                )?;
+            }
+            Some(KeyboardDragAction::Confirm) => self.end_drag()?,
+            Some(KeyboardDragAction::Cancel) => self.cancel_drag()?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn handle_enter_notify(
+        &mut self,
+        event: EnterNotifyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let client = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_frame(event.event)
+        {
+            client
+        } else {
+            return Ok(());
+        };
+
+        let target = self
+            .titlebar_button_at(client, event.event_x as i32, event.event_y as i32)?
+            .map(|button| (client, button));
+        self.client_exec.hover_titlebar_button(target);
+        Ok(())
+    }
+
+    fn handle_leave_notify(
+        &mut self,
+        event: LeaveNotifyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self
+            .client_exec
+            .container()
+            .query_client_from_frame(event.event)
+            .is_some()
+        {
+            self.client_exec.hover_titlebar_button(None);
+        }
+        Ok(())
+    }
+
+    fn titlebar_button_at(
+        &self,
+        client: Client<Window>,
+        x_on_frame: i32,
+        y_on_frame: i32,
+    ) -> Result<Option<TitlebarButton>, Box<dyn std::error::Error>> {
+        Ok(self
+            .client_exec
+            .get_client_geometry(client)
+            .check_button_by_position_on_frame(
+                x_on_frame,
+                y_on_frame,
+                &self.session.config().titlebar_config,
+            )
+            .map(|button| shown_button(button, self.client_exec.is_responding(client))))
+    }
+
     fn handle_motion_notify(
         &mut self,
         event: MotionNotifyEvent,
@@ -154,31 +719,32 @@ impl<'a> Handler<'a> {
         {
             drag_state
         } else {
+            // update the hovered titlebar button while not dragging
+            let target = self
+                .titlebar_button_at(client, event.event_x as i32, event.event_y as i32)?
+                .map(|button| (client, button));
+            self.client_exec.hover_titlebar_button(target);
             return Ok(());
         };
 
         let root_position = (event.root_x as i32, event.root_y as i32);
         let diff_position = (
-            root_position.0 - drag_state.last_root_position().0,
-            root_position.1 - drag_state.last_root_position().1,
+            root_position.0 - drag_state.start_root_position().0,
+            root_position.1 - drag_state.start_root_position().1,
         );
 
-        let client_geometry = self
-            .client_exec
-            .get_client_geometry(client)?
-            .move_resize_on_control(
-                diff_position.0,
-                diff_position.1,
-                drag_state.geometry_control(),
-            );
+        let client_geometry = drag_state.start_geometry().move_resize_on_control(
+            diff_position.0,
+            diff_position.1,
+            drag_state.geometry_control(),
+            &self.client_exec.get_size_hints(client),
+        );
 
         self.client_exec.apply_geometry(
             client,
             client_geometry,
             drag_state.geometry_control().is_resize(),
         )?;
-
-        self.drag_state.change_root_position(root_position);
         Ok(())
     }
 
@@ -186,31 +752,69 @@ impl<'a> Handler<'a> {
         &mut self,
         event: ConfigureRequestEvent,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        // just configure the window
-        let values: ConfigureWindowAux = ConfigureWindowAux::from_configure_request(&event)
-            .stack_mode(None)
-            .border_width(0);
-
-        self.session
-            .connection()
-            .configure_window(event.window, &values)?;
-
-        if let Some(client) = self
+        let client = if let Some(client) = self
             .client_exec
             .container()
             .query_client_from_app(event.window)
         {
-            // if the window is binded to a frame, move the frame and configure the window
-            let client_geometry = ClientGeometry::from_app(
-                event.x as i32,
-                event.y as i32,
-                event.width as u32,
-                event.height as u32,
-                self.session.config().frame_config,
-            );
-            let resized = values.width.is_some() || values.height.is_some();
-            self.client_exec
-                .apply_geometry(client, client_geometry, resized)?;
+            client
+        } else {
+            // just configure the window not managed yet
+            let values = ConfigureWindowAux::from_configure_request(&event).stack_mode(None);
+            self.session
+                .connection()
+                .configure_window(event.window, &values)?;
+            return Ok(());
+        };
+
+        // maximized and fullscreen clients keep the geometry given by the window manager
+        if self
+            .client_exec
+            .get_window_state(client)
+            .has_fixed_geometry()
+        {
+            self.client_exec.send_configure_notify(client)?;
+            return Ok(());
+        }
+
+        // the app is reparented, so the request is applied to the frame
+        // ICCCM 4.1.5: the position is the one without the frame, at the reference point of the gravity
+        let gravity = self.client_exec.get_size_hints(client).gravity;
+        let current_geometry = self.client_exec.get_client_geometry(client);
+        let app_geometry = current_geometry.parse_as_unframed_app(gravity);
+        let value = |flag: ConfigWindow, requested: i32, current: i32| {
+            if event.value_mask.contains(flag) {
+                requested
+            } else {
+                current
+            }
+        };
+        let client_geometry = ClientGeometry::from_unframed_app(
+            Geometry {
+                x: value(ConfigWindow::X, event.x as i32, app_geometry.x),
+                y: value(ConfigWindow::Y, event.y as i32, app_geometry.y),
+                width: value(
+                    ConfigWindow::WIDTH,
+                    event.width as i32,
+                    app_geometry.width as i32,
+                ) as u32,
+                height: value(
+                    ConfigWindow::HEIGHT,
+                    event.height as i32,
+                    app_geometry.height as i32,
+                ) as u32,
+            },
+            gravity,
+            self.client_exec.client_frame_config(client),
+        );
+        let resized = client_geometry.parse_as_app().width != app_geometry.width
+            || client_geometry.parse_as_app().height != app_geometry.height;
+        self.client_exec
+            .apply_geometry(client, client_geometry, resized)?;
+
+        // ICCCM 4.1.5: the app is notified even if nothing has changed
+        if !resized {
+            self.client_exec.send_configure_notify(client)?;
         }
         Ok(())
     }
@@ -219,6 +823,109 @@ impl<'a> Handler<'a> {
         &mut self,
         event: MapRequestEvent,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        // the minimized client asks to be mapped again
+        if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_app(event.window)
+        {
+            self.client_exec.restore_client(client)?;
+            return Ok(());
+        }
+
+        // the app may be destroyed right after asking to be mapped
+        match self.manage_window(event.window, None) {
+            Ok(_) => Ok(()),
+            Err(e) if is_x11_error(&*e) => {
+                info!(
+                    "Failed to manage {}, which may be gone: {}",
+                    event.window, e
+                );
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Frames the windows mapped before the window manager started.
+    /// The children of the root are listed from the bottom, so the stacking order is kept.
+    /// The state saved before a restart is restored.
+    pub fn adopt_existing_windows(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.client_exec.init_monitors()?;
+        self.client_exec.init_workspaces()?;
+        let connection = self.session.connection();
+        // keep the windows from changing during the scan
+        connection.grab_server()?;
+        // the server is ungrabbed even if the scan fails
+        let scanned = self.adopt_children();
+        connection.ungrab_server()?;
+
+        if let Some(client) = scanned? {
+            self.client_exec.raise_client(client, None)?;
+        }
+        Ok(())
+    }
+
+    /// Manages the viewable children of the root, returning the client focused before a restart.
+    /// A window which cannot be managed is skipped, so it does not stop the others from being adopted.
+    fn adopt_children(&mut self) -> Result<Option<Client<Window>>, Box<dyn std::error::Error>> {
+        let connection = self.session.connection();
+        let tree = connection.query_tree(self.session.screen().root)?.reply()?;
+        let attributes = tree
+            .children
+            .iter()
+            .map(|window| connection.get_window_attributes(*window))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut focused_client = None;
+        for (window, attributes) in tree.children.iter().zip(attributes) {
+            let attributes = match attributes.reply() {
+                Ok(attributes) => attributes,
+                // the window has been destroyed before the grab
+                Err(_) => continue,
+            };
+            if attributes.override_redirect || attributes.map_state != MapState::VIEWABLE {
+                continue;
+            }
+            info!("adopting the existing window {:?}", window);
+            match self.adopt_window(*window) {
+                Ok(Some(client)) => focused_client = Some(client),
+                Ok(None) => {}
+                Err(e) if is_x11_error(&*e) => {
+                    error!("Failed to adopt the window {:?}: {}", window, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(focused_client)
+    }
+
+    /// Manages the window with the state saved before a restart.
+    /// Returns the client if it was focused before the restart.
+    fn adopt_window(
+        &mut self,
+        window: Window,
+    ) -> Result<Option<Client<Window>>, Box<dyn std::error::Error>> {
+        let restart_state = self.client_exec.take_restart_state(window)?;
+        let client = self.manage_window(window, restart_state.map(|state| state.geometry))?;
+
+        if let Some(restart_state) = restart_state {
+            self.client_exec
+                .restore_restart_state(client, &restart_state)?;
+            if restart_state.focused {
+                return Ok(Some(client));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Creates a frame for the app and starts managing it.
+    /// The app is placed at `app_geometry` if given, otherwise at its current position.
+    fn manage_window(
+        &mut self,
+        window: Window,
+        app_geometry: Option<Geometry>,
+    ) -> Result<Client<Window>, Box<dyn std::error::Error>> {
         let frame = self.session.connection().generate_id()?;
 
         let frame_colormap = self.session.connection().generate_id()?;
@@ -230,34 +937,93 @@ impl<'a> Handler<'a> {
             self.session.cairo_session().visual_type().visual_id,
         )?;
 
+        match self.frame_window(window, frame, frame_colormap, app_geometry) {
+            Ok(client) => Ok(client),
+            Err(e) => {
+                // the half-built frame is not left behind, whether the client has been added or not
+                let client = self.client_exec.container().query_client_from_app(window);
+                if let Some(client) = client {
+                    self.client_exec.unmanage_client(client)?;
+                } else {
+                    self.session.connection().destroy_window(frame)?;
+                }
+                self.session.connection().free_colormap(frame_colormap)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Creates the frame with the colormap, reparents the app into it, and reads the hints of the app.
+    fn frame_window(
+        &mut self,
+        window: Window,
+        frame: Window,
+        frame_colormap: Colormap,
+        app_geometry: Option<Geometry>,
+    ) -> Result<Client<Window>, Box<dyn std::error::Error>> {
         let frame_values = CreateWindowAux::default()
             .event_mask(
                 EventMask::BUTTON_PRESS
                     | EventMask::BUTTON_RELEASE
                     | EventMask::POINTER_MOTION
-                    | EventMask::EXPOSURE,
+                    | EventMask::ENTER_WINDOW
+                    | EventMask::LEAVE_WINDOW
+                    | EventMask::EXPOSURE
+                    // the requests and notifications of the reparented app
+                    | EventMask::SUBSTRUCTURE_REDIRECT
+                    | EventMask::SUBSTRUCTURE_NOTIFY,
             )
             .border_pixel(0)
             .background_pixel(0)
             .colormap(frame_colormap);
 
-        let original_geometry = self
-            .session
-            .connection()
-            .get_geometry(event.window)?
-            .reply()?;
-
-        let frame_config = self.session.config().frame_config;
-
-        let client_geometry: ClientGeometry = ClientGeometry::from_app(
-            original_geometry.x as i32,
-            original_geometry.y as i32 + frame_config.titlebar_height as i32,
-            original_geometry.width as u32,
-            original_geometry.height as u32,
-            frame_config,
-        );
+        // read once for the window type, the placement and the hints
+        let transient_for = self.client_exec.fetch_transient_for(window)?;
+        let window_type = self
+            .client_exec
+            .fetch_window_type(window, transient_for.is_some())?;
+        let frame_config = self.client_exec.frame_config_for(window_type);
 
-        let app_geometry = client_geometry.parse_as_app();
+        let client_geometry: ClientGeometry = if let Some(app_geometry) = app_geometry {
+            ClientGeometry::from_app(
+                app_geometry.x,
+                app_geometry.y,
+                app_geometry.width,
+                app_geometry.height,
+                frame_config,
+            )
+        } else {
+            // the frame is placed around the app by the gravity, so the app is not covered by it
+            let original_geometry = self.session.connection().get_geometry(window)?.reply()?;
+            let gravity = self.client_exec.fetch_size_hints(window)?.gravity;
+            let client_geometry = ClientGeometry::from_unframed_app(
+                Geometry {
+                    x: original_geometry.x as i32,
+                    y: original_geometry.y as i32,
+                    width: original_geometry.width as u32,
+                    height: original_geometry.height as u32,
+                },
+                gravity,
+                frame_config,
+            );
+            let client_geometry = if window_type.is_centered_over_parent() {
+                client_geometry.centered_over(
+                    self.client_exec
+                        .parent_area(transient_for, client_geometry.parse_as_frame()),
+                )
+            } else {
+                client_geometry
+            };
+            // undecorated windows such as docks are placed by themselves
+            if window_type.is_decorated() {
+                client_geometry.clamped_into(
+                    self.client_exec
+                        .work_area_for(client_geometry.parse_as_frame()),
+                )
+            } else {
+                client_geometry
+            }
+        };
 
         let frame_geometry = client_geometry.parse_as_frame();
 
@@ -275,28 +1041,25 @@ impl<'a> Handler<'a> {
             &frame_values,
         )?;
 
-        self.session.connection().configure_window(
-            event.window,
-            &ConfigureWindowAux::default()
-                .stack_mode(x11rb::protocol::xproto::StackMode::ABOVE)
-                .x(app_geometry.x)
-                .y(app_geometry.y)
-                .width(app_geometry.width)
-                .height(app_geometry.height),
-        )?;
-
         self.session.connection().change_window_attributes(
-            event.window,
+            window,
             &ChangeWindowAttributesAux::default().event_mask(EventMask::PROPERTY_CHANGE),
         )?;
 
-        self.session.connection().map_window(frame)?;
-        self.session.connection().map_window(event.window)?;
+        let client = self
+            .client_exec
+            .add_client(window, frame, client_geometry, window_type)?;
 
-        self.client_exec
-            .add_client(event.window, frame, client_geometry)?;
+        self.session.connection().map_window(window)?;
+        self.session.connection().map_window(frame)?;
+        self.client_exec.set_wm_state(client, WmState::Normal)?;
+        self.client_exec.update_hints(client, transient_for)?;
+        self.client_exec.update_strut(client)?;
+        self.client_exec.update_sync_counter(client)?;
+        self.client_exec.load_desktop(client)?;
+        self.client_exec.load_window_state(client)?;
 
-        Ok(())
+        Ok(client)
     }
 
     fn handle_map_notify(
@@ -313,7 +1076,7 @@ impl<'a> Handler<'a> {
             return Ok(());
         };
 
-        self.client_exec.raise_client(client)?;
+        self.client_exec.raise_client(client, None)?;
         Ok(())
     }
 
@@ -331,9 +1094,54 @@ impl<'a> Handler<'a> {
             return Ok(());
         };
 
-        self.session.connection().destroy_window(client.frame_id)?;
+        // ignore the unmaps done by the window manager
+        if self.client_exec.consume_pending_unmap(client) {
+            return Ok(());
+        }
 
-        self.client_exec.remove_client(client);
+        self.client_exec.unmanage_client(client)?;
         Ok(())
     }
+
+    fn handle_destroy_notify(
+        &mut self,
+        event: DestroyNotifyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // the app destroyed without being unmapped, e.g. while it is minimized
+        let client = if let Some(client) = self
+            .client_exec
+            .container()
+            .query_client_from_app(event.window)
+        {
+            client
+        } else {
+            return Ok(());
+        };
+
+        self.client_exec.unmanage_client(client)?;
+        Ok(())
+    }
+}
+
+/// Whether the error is an error reply of the X11 server, such as BadWindow for a window
+/// destroyed before its events are handled. The other errors are those of the connection.
+fn is_x11_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<ReplyError>(),
+        Some(ReplyError::X11Error(_))
+    )
+}
+
+/// Logs an error reply of the X11 server instead of failing, while the other errors are returned.
+fn ignore_x11_error(
+    result: Result<(), Box<dyn std::error::Error>>,
+    action: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match result {
+        Err(e) if is_x11_error(&*e) => {
+            error!("Failed to {}: {}", action, e);
+            Ok(())
+        }
+        result => result,
+    }
 }
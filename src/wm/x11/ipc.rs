@@ -0,0 +1,218 @@
+use std::{
+    fs::DirBuilder,
+    io::{ErrorKind, Read, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use log::{info, warn};
+use rustix::{net::sockopt::get_socket_peercred, process::getuid};
+
+use super::command::Command;
+
+// longer requests are not commands, and are rejected
+const MAX_REQUEST_LENGTH: usize = 256;
+
+/// Directory of the control socket, which only the user can enter.
+/// Without $XDG_RUNTIME_DIR, a directory of the user is made in the temporary directory,
+/// as the socket must not be reachable by the other users sharing it.
+fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir),
+        None => std::env::temp_dir().join(format!("clearwm-{}", getuid().as_raw())),
+    }
+}
+
+/// Creates the directory of the fallback runtime_dir, or checks that the existing one
+/// is a directory private to the user, not one prepared by another user.
+fn create_private_dir(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    match DirBuilder::new().mode(0o700).create(path) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e.into()),
+    }
+    // a symbolic link is not followed, as its target may belong to anyone
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir()
+        || metadata.uid() != getuid().as_raw()
+        || metadata.permissions().mode() & 0o077 != 0
+    {
+        return Err(format!("{:?} is not a directory private to the user", path).into());
+    }
+    Ok(())
+}
+
+/// Path of the control socket of the window manager running on the display in $DISPLAY.
+pub fn socket_path() -> PathBuf {
+    let runtime_dir = runtime_dir();
+    let display = std::env::var("DISPLAY").unwrap_or_default();
+    // the display name may contain a hostname, but not a path separator
+    let display = display.replace('/', "_");
+    runtime_dir.join(format!("clearwm{}.sock", display))
+}
+
+/// Sends the command through the connected control socket, and waits for the reply.
+pub fn send_command(
+    mut stream: UnixStream,
+    command: Command,
+) -> Result<(), Box<dyn std::error::Error>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    writeln!(stream, "{}", command.name())?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    match reply.trim_end().strip_prefix("error: ") {
+        Some(message) => Err(message.to_string().into()),
+        None => Ok(()),
+    }
+}
+
+/// Connection to the control socket, with the request read so far.
+struct IpcConnection {
+    stream: UnixStream,
+    request: Vec<u8>,
+}
+
+/// Connection waiting for the result of its command, which is closed after the reply.
+#[derive(Debug)]
+pub struct IpcReply {
+    stream: UnixStream,
+}
+
+impl IpcReply {
+    /// Replies with `ok` or `error: <message>`.
+    pub fn send(mut self, result: Result<(), String>) {
+        let reply = match result {
+            Ok(()) => String::from("ok\n"),
+            Err(message) => format!("error: {}\n", message),
+        };
+        // the client is waiting for the short reply, which fits in the socket buffer
+        if let Err(e) = self.stream.write_all(reply.as_bytes()) {
+            warn!("Failed to reply to the control socket: {}", e);
+        }
+    }
+}
+
+/// Control socket accepting a command name per connection, such as `reload\n`.
+/// Each command is answered with `ok` or `error: <message>` after it has run.
+pub struct IpcServer {
+    listener: UnixListener,
+    path: PathBuf,
+    connections: Vec<IpcConnection>,
+}
+
+impl IpcServer {
+    /// Binds the socket, replacing the one left by a window manager which did not exit cleanly.
+    /// The caller must own the manager selection, so no other window manager uses the socket.
+    pub fn bind() -> Result<Self, Box<dyn std::error::Error>> {
+        if std::env::var_os("XDG_RUNTIME_DIR").is_none() {
+            create_private_dir(&runtime_dir())?;
+        }
+        let path = socket_path();
+        match std::fs::remove_file(&path) {
+            Ok(()) => info!("Removed the stale control socket {:?}", path),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        info!("Listening on {:?}", path);
+        Ok(Self {
+            listener,
+            path,
+            connections: Vec::new(),
+        })
+    }
+
+    pub fn listener(&self) -> &UnixListener {
+        &self.listener
+    }
+
+    pub fn streams(&self) -> impl Iterator<Item = &UnixStream> {
+        self.connections.iter().map(|connection| &connection.stream)
+    }
+
+    /// Accepts the pending connections.
+    /// Connections from the other users are closed, as they must not control the window manager.
+    pub fn accept(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    let peer_uid = get_socket_peercred(&stream)?.uid;
+                    if peer_uid != getuid() {
+                        warn!(
+                            "Rejected a connection to the control socket from uid {}",
+                            peer_uid.as_raw()
+                        );
+                        continue;
+                    }
+                    stream.set_nonblocking(true)?;
+                    self.connections.push(IpcConnection {
+                        stream,
+                        request: Vec::new(),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Reads the connection at the index, returning the command if the request is complete.
+    /// The connection of the command is returned with it to reply with the result,
+    /// and the others are closed when the request is unknown or the read fails.
+    pub fn read(&mut self, index: usize) -> Option<(Command, IpcReply)> {
+        let connection = &mut self.connections[index];
+        let mut buffer = [0; MAX_REQUEST_LENGTH];
+        let complete = loop {
+            match connection.stream.read(&mut buffer) {
+                // the request without a newline ends at the end of the stream
+                Ok(0) => break true,
+                Ok(length) => {
+                    connection.request.extend_from_slice(&buffer[..length]);
+                    if connection.request.contains(&b'\n')
+                        || connection.request.len() > MAX_REQUEST_LENGTH
+                    {
+                        break true;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break false,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    warn!("Failed to read the control socket: {}", e);
+                    self.connections.remove(index);
+                    return None;
+                }
+            }
+        };
+        if !complete {
+            return None;
+        }
+
+        let connection = self.connections.remove(index);
+        let request = String::from_utf8_lossy(&connection.request);
+        let name = request.lines().next().unwrap_or_default().trim();
+        let reply = IpcReply {
+            stream: connection.stream,
+        };
+        match Command::from_name(name) {
+            Some(command) => Some((command, reply)),
+            None => {
+                reply.send(Err(format!("unknown command {:?}", name)));
+                None
+            }
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Failed to remove the control socket {:?}: {}", self.path, e);
+        }
+    }
+}
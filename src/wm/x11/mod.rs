@@ -1,5 +1,10 @@
 mod client_executor;
+pub mod command;
+mod ewmh;
 pub mod graphics;
 mod handler;
+mod ipc;
+mod poller;
+mod selection;
 pub mod session;
 pub mod window_manager;
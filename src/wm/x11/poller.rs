@@ -0,0 +1,208 @@
+use std::{
+    io::{ErrorKind, Read},
+    os::unix::net::UnixStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use log::{error, info};
+use rustix::event::{poll, PollFd, PollFlags};
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    flag, low_level,
+};
+use x11rb::{connection::Connection, protocol::Event, xcb_ffi::XCBConnection};
+
+use super::{
+    command::Command,
+    ipc::{IpcReply, IpcServer},
+};
+
+/// Signals delivered as commands. SIGHUP reloads the config file, SIGINT and SIGTERM stop the window manager.
+const SIGNAL_COMMANDS: [(i32, Command); 3] = [
+    (SIGHUP, Command::Reload),
+    (SIGINT, Command::Exit),
+    (SIGTERM, Command::Exit),
+];
+
+/// Signals caught by flags, with a self-pipe which wakes up the poll.
+struct SignalSource {
+    flags: Vec<(i32, Command, Arc<AtomicBool>)>,
+    pipe: UnixStream,
+}
+
+impl SignalSource {
+    fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let (pipe, pipe_write) = UnixStream::pair()?;
+        pipe.set_nonblocking(true)?;
+        pipe_write.set_nonblocking(true)?;
+
+        let mut flags = Vec::new();
+        for (signal, command) in SIGNAL_COMMANDS {
+            let signal_flag = Arc::new(AtomicBool::new(false));
+            // the flag is set before the pipe wakes up the poll, as the actions run in order
+            flag::register(signal, Arc::clone(&signal_flag))?;
+            low_level::pipe::register(signal, pipe_write.try_clone()?)?;
+            flags.push((signal, command, signal_flag));
+        }
+        Ok(Self { flags, pipe })
+    }
+
+    fn take_commands(&mut self) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let mut buffer = [0; 64];
+        loop {
+            match self.pipe.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut commands = Vec::new();
+        for (signal, command, signal_flag) in &self.flags {
+            if signal_flag.swap(false, Ordering::SeqCst) {
+                info!("Received signal {}, running {:?}", signal, command);
+                commands.push(*command);
+            }
+        }
+        Ok(commands)
+    }
+}
+
+/// Command to run, with the connection of the control socket waiting for its result.
+#[derive(Debug)]
+pub struct Request {
+    pub command: Command,
+    pub reply: Option<IpcReply>,
+}
+
+/// What woke up the main loop.
+#[derive(Debug, Default)]
+pub struct Wakeup {
+    /// The first X11 event, the rest of which are read by poll_for_event.
+    pub event: Option<Event>,
+    /// Commands from the signals and the control socket.
+    pub commands: Vec<Request>,
+}
+
+/// Waits for the X11 connection, the signals, the control socket, and the deadline of the timers at once.
+pub struct Poller {
+    signals: Option<SignalSource>,
+    ipc: Option<IpcServer>,
+}
+
+impl Poller {
+    /// Starts catching the signals. A poller without the signals is still usable,
+    /// so the failure is only logged.
+    pub fn new() -> Self {
+        let signals = SignalSource::new()
+            .map_err(|e| error!("Failed to set up signal handling: {}", e))
+            .ok();
+        Self { signals, ipc: None }
+    }
+
+    /// Starts accepting commands on the control socket.
+    /// It must be called after the manager selection is acquired.
+    pub fn listen(&mut self) {
+        self.ipc = IpcServer::bind()
+            .map_err(|e| error!("Failed to set up the control socket: {}", e))
+            .ok();
+    }
+
+    /// Stops accepting commands and removes the control socket.
+    pub fn close(&mut self) {
+        self.ipc = None;
+    }
+
+    /// Waits until something happens, or the deadline has passed.
+    pub fn wait(
+        &mut self,
+        connection: &XCBConnection,
+        deadline: Option<Instant>,
+    ) -> Result<Wakeup, Box<dyn std::error::Error>> {
+        // events may have been read into the queue of the connection already,
+        // and the other sources are only checked without blocking then
+        let event = connection.poll_for_event()?;
+        let timeout_millis = if event.is_some() {
+            0
+        } else if let Some(deadline) = deadline {
+            // round up not to wake up just before the deadline
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32
+        } else {
+            -1
+        };
+
+        let (signals_ready, listener_ready, streams_ready) = {
+            let mut fds = vec![PollFd::new(connection, PollFlags::IN)];
+            if let Some(signals) = &self.signals {
+                fds.push(PollFd::new(&signals.pipe, PollFlags::IN));
+            }
+            if let Some(ipc) = &self.ipc {
+                fds.push(PollFd::new(ipc.listener(), PollFlags::IN));
+                fds.extend(
+                    ipc.streams()
+                        .map(|stream| PollFd::new(stream, PollFlags::IN)),
+                );
+            }
+            match poll(&mut fds, timeout_millis) {
+                Ok(_) => {}
+                // a signal is handled by the next wait, as the pipe is readable then
+                Err(rustix::io::Errno::INTR) => {
+                    return Ok(Wakeup {
+                        event,
+                        commands: Vec::new(),
+                    })
+                }
+                Err(e) => return Err(e.into()),
+            }
+            let mut ready = fds[1..].iter().map(|fd| !fd.revents().is_empty());
+            let signals_ready = self.signals.is_some() && ready.next().unwrap_or(false);
+            let listener_ready = self.ipc.is_some() && ready.next().unwrap_or(false);
+            (signals_ready, listener_ready, ready.collect::<Vec<_>>())
+        };
+
+        let mut commands = Vec::new();
+        if let (Some(signals), true) = (&mut self.signals, signals_ready) {
+            commands.extend(signals.take_commands()?.into_iter().map(|command| Request {
+                command,
+                reply: None,
+            }));
+        }
+        if let Some(ipc) = &mut self.ipc {
+            let mut ipc_commands = Vec::new();
+            // read from the last, as the finished connections are removed
+            for (index, _) in streams_ready
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, ready)| **ready)
+            {
+                if let Some((command, reply)) = ipc.read(index) {
+                    info!("Received {:?} on the control socket", command);
+                    ipc_commands.push(Request {
+                        command,
+                        reply: Some(reply),
+                    });
+                }
+            }
+            commands.extend(ipc_commands.into_iter().rev());
+            if listener_ready {
+                if let Err(e) = ipc.accept() {
+                    error!("Failed to accept a connection to the control socket: {}", e);
+                }
+            }
+        }
+
+        let event = match event {
+            Some(event) => Some(event),
+            None => connection.poll_for_event()?,
+        };
+        Ok(Wakeup { event, commands })
+    }
+}
@@ -0,0 +1,120 @@
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{
+            AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt,
+            CreateWindowAux, EventMask, PropMode, Timestamp, Window, WindowClass,
+        },
+        Event,
+    },
+    wrapper::ConnectionExt as _,
+};
+
+use super::session::X11Session;
+
+/// Ownership of the `WM_S{screen}` manager selection defined in ICCCM 2.8.
+pub struct ManagerSelection {
+    window: Window,
+    timestamp: Timestamp,
+}
+
+/// Result of acquiring the manager selection.
+pub enum SelectionAcquired {
+    Acquired(ManagerSelection),
+    /// The selection is taken from the previous owner, which is expected to destroy its window.
+    Replaced(ManagerSelection, Window),
+    /// Another window manager owns the selection and `replace` is not requested.
+    Owned(Window),
+}
+
+impl ManagerSelection {
+    pub fn acquire(
+        session: &X11Session,
+        replace: bool,
+    ) -> Result<SelectionAcquired, Box<dyn std::error::Error>> {
+        let connection = session.connection();
+        let selection = session.manager_selection();
+
+        let previous_owner = connection.get_selection_owner(selection)?.reply()?.owner;
+        if previous_owner != x11rb::NONE {
+            if !replace {
+                return Ok(SelectionAcquired::Owned(previous_owner));
+            }
+            // DestroyNotify tells when the previous owner has finished
+            connection.change_window_attributes(
+                previous_owner,
+                &ChangeWindowAttributesAux::default().event_mask(EventMask::STRUCTURE_NOTIFY),
+            )?;
+        }
+
+        let window = connection.generate_id()?;
+        let screen = session.screen();
+        connection.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            -1,
+            -1,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::default()
+                .override_redirect(1)
+                .event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+
+        // CurrentTime must not be used for the selection, so get a timestamp by a property change
+        connection.change_property8(
+            PropMode::APPEND,
+            window,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            &[],
+        )?;
+        connection.flush()?;
+        let timestamp = loop {
+            if let Event::PropertyNotify(event) = connection.wait_for_event()? {
+                if event.window == window {
+                    break event.time;
+                }
+            }
+        };
+
+        connection.set_selection_owner(window, selection, timestamp)?;
+        if connection.get_selection_owner(selection)?.reply()?.owner != window {
+            connection.destroy_window(window)?;
+            return Ok(SelectionAcquired::Owned(previous_owner));
+        }
+
+        // tell the clients that a new window manager has started
+        let atoms = session.atoms();
+        let event = ClientMessageEvent::new(
+            32,
+            screen.root,
+            atoms.MANAGER,
+            [timestamp, selection, window, 0, 0],
+        );
+        connection.send_event(false, screen.root, EventMask::STRUCTURE_NOTIFY, event)?;
+
+        let manager_selection = ManagerSelection { window, timestamp };
+        if previous_owner != x11rb::NONE {
+            Ok(SelectionAcquired::Replaced(
+                manager_selection,
+                previous_owner,
+            ))
+        } else {
+            Ok(SelectionAcquired::Acquired(manager_selection))
+        }
+    }
+
+    /// Gives up the selection, so the next window manager can start.
+    pub fn release(self, session: &X11Session) -> Result<(), Box<dyn std::error::Error>> {
+        let connection = session.connection();
+        connection.set_selection_owner(x11rb::NONE, session.manager_selection(), self.timestamp)?;
+        connection.destroy_window(self.window)?;
+        connection.flush()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,14 @@
+use std::cell::{Ref, RefCell};
+
 use x11rb::{
-    atom_manager, connection::Connection, protocol::xproto::Screen, xcb_ffi::XCBConnection,
+    atom_manager,
+    connection::Connection,
+    protocol::{
+        randr::ConnectionExt as _,
+        sync::ConnectionExt as _,
+        xproto::{Atom, ConnectionExt, Screen},
+    },
+    xcb_ffi::XCBConnection,
 };
 
 use crate::config::WindowManagerConfig;
@@ -8,20 +17,86 @@ use super::graphics::CairoSession;
 
 atom_manager! {
     pub AtomCollection: AtomCollectionCookie {
+        _NET_SUPPORTED,
+        _NET_SUPPORTING_WM_CHECK,
+        _NET_CLIENT_LIST,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_ACTIVE_WINDOW,
+        _NET_WORKAREA,
+        _NET_NUMBER_OF_DESKTOPS,
+        _NET_CURRENT_DESKTOP,
+        _NET_DESKTOP_NAMES,
         _NET_WM_NAME,
+        _NET_WM_DESKTOP,
+        _NET_WM_MOVERESIZE,
+        _NET_WM_SYNC_REQUEST,
+        _NET_WM_SYNC_REQUEST_COUNTER,
+        _NET_FRAME_EXTENTS,
+        _NET_REQUEST_FRAME_EXTENTS,
+        _NET_WM_STRUT,
+        _NET_WM_STRUT_PARTIAL,
+        _NET_WM_STATE,
+        _NET_WM_STATE_FULLSCREEN,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_BELOW,
+        _NET_WM_STATE_STICKY,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_SHADED,
+        _NET_WM_STATE_SKIP_TASKBAR,
+        _NET_WM_STATE_SKIP_PAGER,
+        _NET_WM_STATE_DEMANDS_ATTENTION,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_DESKTOP,
+        _NET_WM_WINDOW_TYPE_DOCK,
+        _NET_WM_WINDOW_TYPE_TOOLBAR,
+        _NET_WM_WINDOW_TYPE_MENU,
+        _NET_WM_WINDOW_TYPE_UTILITY,
+        _NET_WM_WINDOW_TYPE_SPLASH,
+        _NET_WM_WINDOW_TYPE_DIALOG,
+        _NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
+        _NET_WM_WINDOW_TYPE_POPUP_MENU,
+        _NET_WM_WINDOW_TYPE_TOOLTIP,
+        _NET_WM_WINDOW_TYPE_NOTIFICATION,
+        _NET_WM_WINDOW_TYPE_COMBO,
+        _NET_WM_WINDOW_TYPE_DND,
+        _NET_WM_WINDOW_TYPE_NORMAL,
         WM_NAME,
         UTF8_STRING,
         STRING,
+        COMPOUND_TEXT,
+        WM_STATE,
+        WM_PROTOCOLS,
+        WM_DELETE_WINDOW,
+        WM_CLIENT_MACHINE,
+        WM_WINDOW_ROLE,
+        _NET_WM_PID,
+        _NET_WM_PING,
+        _NET_WM_ICON,
+        MANAGER,
+        _CLEARWM_COMMAND,
+        _CLEARWM_RELOAD,
+        _CLEARWM_EXIT,
+        _CLEARWM_RESTART,
+        _CLEARWM_RESTART_STATE,
     }
 }
 
 /// X11Session connects to the X11 server and provides static information about the X11 server and the window manager configuration.
+/// The configuration can be replaced at runtime by reloading the config file.
 pub struct X11Session {
     connection: XCBConnection,
     cairo_session: CairoSession,
     atoms: AtomCollection,
     screen_num: usize,
-    window_manager_config: WindowManagerConfig,
+    // WM_S{screen_num}, owned by the window manager of the screen
+    manager_selection: Atom,
+    // whether the server has the SYNC extension for _NET_WM_SYNC_REQUEST
+    sync_supported: bool,
+    // whether the server has RandR 1.5, which lists the monitors
+    randr_supported: bool,
+    window_manager_config: RefCell<WindowManagerConfig>,
 }
 
 impl X11Session {
@@ -31,12 +106,29 @@ impl X11Session {
         let (connection, screen_num) = XCBConnection::connect(None)?;
         let cairo_session = CairoSession::create(&connection.setup().roots[screen_num])?;
         let atoms = AtomCollection::new(&connection)?.reply()?;
+        let manager_selection = connection
+            .intern_atom(false, format!("WM_S{}", screen_num).as_bytes())?
+            .reply()?
+            .atom;
+        let sync_supported = match connection.sync_initialize(3, 1) {
+            Ok(cookie) => cookie.reply().is_ok(),
+            Err(_) => false,
+        };
+        let randr_supported = match connection.randr_query_version(1, 5) {
+            Ok(cookie) => cookie
+                .reply()
+                .is_ok_and(|version| (version.major_version, version.minor_version) >= (1, 5)),
+            Err(_) => false,
+        };
         Ok(Self {
             connection,
             cairo_session,
             atoms,
             screen_num,
-            window_manager_config,
+            manager_selection,
+            sync_supported,
+            randr_supported,
+            window_manager_config: RefCell::new(window_manager_config),
         })
     }
 
@@ -52,8 +144,30 @@ impl X11Session {
         &self.connection.setup().roots[self.screen_num]
     }
 
-    pub fn config(&self) -> &WindowManagerConfig {
-        &self.window_manager_config
+    pub fn screen_num(&self) -> usize {
+        self.screen_num
+    }
+
+    pub fn manager_selection(&self) -> Atom {
+        self.manager_selection
+    }
+
+    pub fn sync_supported(&self) -> bool {
+        self.sync_supported
+    }
+
+    pub fn randr_supported(&self) -> bool {
+        self.randr_supported
+    }
+
+    pub fn config(&self) -> Ref<'_, WindowManagerConfig> {
+        self.window_manager_config.borrow()
+    }
+
+    /// Replaces the configuration for a live reload.
+    /// Clients have to be reconfigured by the caller.
+    pub fn set_config(&self, window_manager_config: WindowManagerConfig) {
+        *self.window_manager_config.borrow_mut() = window_manager_config;
     }
 
     pub fn atoms(&self) -> &AtomCollection {
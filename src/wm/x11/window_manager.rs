@@ -1,14 +1,69 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
 use x11rb::{
     connection::Connection,
-    protocol::xproto::{ChangeWindowAttributesAux, ConnectionExt, EventMask},
+    protocol::{
+        xproto::{ChangeWindowAttributesAux, ConnectionExt, EventMask, Window},
+        Event,
+    },
+};
+
+use super::{
+    ewmh::Ewmh,
+    handler::Handler,
+    poller::{Poller, Request},
+    selection::{ManagerSelection, SelectionAcquired},
+    session::X11Session,
 };
 
-use super::{handler::Handler, session::X11Session};
+/// How long to wait for the replaced window manager to exit.
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum StartError {
+    /// Another window manager owns the WM_S{screen} selection.
+    SelectionOwned(usize),
+    /// Another window manager is redirecting the root window without owning the selection.
+    RootRedirected,
+}
+
+impl fmt::Display for StartError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StartError::SelectionOwned(screen_num) => write!(
+                f,
+                "another window manager is running on screen {}, use --replace to replace it",
+                screen_num
+            ),
+            StartError::RootRedirected => write!(
+                f,
+                "another window manager is running and does not support replacing"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StartError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitRequest {
+    Exit,
+    /// The binary is executed again, restoring the state of the clients.
+    Restart,
+}
 
 /// X11WindowManager performs the main event loop and dispatches events to the handler.
 pub struct X11WindowManager<'a> {
     session: &'a X11Session,
     handler: Handler<'a>,
+    poller: Poller,
+    // commands received before the handler is ready to run them
+    pending_commands: Vec<Request>,
 }
 
 impl<'a> X11WindowManager<'a> {
@@ -16,26 +71,112 @@ impl<'a> X11WindowManager<'a> {
         Self {
             session,
             handler: Handler::new(session),
+            poller: Poller::new(),
+            pending_commands: Vec::new(),
         }
     }
 
-    pub fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Runs the window manager until it is asked to exit or replaced by another one.
+    /// The apps are given back to the root on exit, so the next window manager can adopt them.
+    /// With `replace`, the running window manager is asked to exit by taking its selection.
+    pub fn start(&mut self, replace: bool) -> Result<ExitRequest, Box<dyn std::error::Error>> {
+        let selection = match ManagerSelection::acquire(self.session, replace)? {
+            SelectionAcquired::Acquired(selection) => selection,
+            SelectionAcquired::Replaced(selection, previous_owner) => {
+                info!("Waiting for the previous window manager to exit");
+                self.wait_for_destroy(previous_owner)?;
+                selection
+            }
+            SelectionAcquired::Owned(owner) => {
+                info!("The manager selection is owned by {:?}", owner);
+                return Err(StartError::SelectionOwned(self.session.screen_num()).into());
+            }
+        };
+
         let root_values = ChangeWindowAttributesAux::default()
             .event_mask(EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY);
 
-        self.session
+        // only one client can select SubstructureRedirect on the root
+        if self
+            .session
             .connection()
             .change_window_attributes(self.session.screen().root, &root_values)?
-            .check()?;
+            .check()
+            .is_err()
+        {
+            selection.release(self.session)?;
+            return Err(StartError::RootRedirected.into());
+        }
+
+        let ewmh = Ewmh::setup(self.session)?;
+        self.poller.listen();
+        self.handler.adopt_existing_windows()?;
+        for request in std::mem::take(&mut self.pending_commands) {
+            self.run_command(request)?;
+        }
 
-        loop {
+        let exit_request = loop {
+            if let Some(exit_request) = self.handler.exit_request() {
+                break exit_request;
+            }
             self.session.connection().flush()?;
-            let mut event_option = Some(self.session.connection().wait_for_event()?);
+            let wakeup = self
+                .poller
+                .wait(self.session.connection(), self.handler.next_deadline())?;
+            for request in wakeup.commands {
+                self.run_command(request)?;
+            }
+            let mut event_option = wakeup.event;
             while let Some(event) = event_option {
                 self.handler.handle_event(event)?;
                 event_option = self.session.connection().poll_for_event()?;
             }
+            self.handler.handle_deadlines()?;
             self.handler.flush_queued()?;
+        };
+
+        info!("Exiting: {:?}", exit_request);
+        if exit_request == ExitRequest::Restart {
+            self.handler.save_restart_state()?;
+        }
+        self.handler.release_clients()?;
+        ewmh.release(self.session)?;
+        // the socket is removed before the next window manager can take the selection and bind it
+        self.poller.close();
+        self.session.connection().change_window_attributes(
+            self.session.screen().root,
+            &ChangeWindowAttributesAux::default().event_mask(EventMask::NO_EVENT),
+        )?;
+        selection.release(self.session)?;
+        Ok(exit_request)
+    }
+
+    /// Runs the command, and replies with its result if the sender is waiting for it.
+    fn run_command(&mut self, request: Request) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self.handler.handle_command(request.command)?;
+        if let Some(reply) = request.reply {
+            reply.send(result);
+        }
+        Ok(())
+    }
+
+    /// Waits until the window is destroyed, or gives up after REPLACE_TIMEOUT.
+    /// The commands received meanwhile are kept for the main loop.
+    fn wait_for_destroy(&mut self, window: Window) -> Result<(), Box<dyn std::error::Error>> {
+        let deadline = Instant::now() + REPLACE_TIMEOUT;
+        while Instant::now() < deadline {
+            let wakeup = self
+                .poller
+                .wait(self.session.connection(), Some(deadline))?;
+            self.pending_commands.extend(wakeup.commands);
+            match wakeup.event {
+                Some(Event::DestroyNotify(event)) if event.window == window => return Ok(()),
+                // the window is gone before its events are selected
+                Some(Event::Error(_)) => return Ok(()),
+                _ => {}
+            }
         }
+        warn!("The previous window manager did not exit in time");
+        Ok(())
     }
 }